@@ -0,0 +1,64 @@
+//! Abstraction over the read-only endpoints, so that code using them can be tested
+//! against something other than fimfiction itself (e.g. `mock::MockApplication`).
+#[cfg(feature = "blocking")]
+use crate::application::Application;
+use crate::error::{ApiError, ChapterCountMismatch};
+use crate::resources::{
+    BlogPostId, BookshelfId, ChapterId, GroupId, Story, StoryId, StoryTagId, UserId,
+};
+use crate::response::{
+    BlogPostResponse, BookshelfResponse, ChapterResponse, ChaptersResponse, GroupResponse,
+    StoryResponse, StoryTagResponse, UserResponse,
+};
+
+/// Retrieval of individual resources by id (and of a story's chapters), as implemented by the blocking `Application`.
+/// Write functions against this trait (rather than `Application`) to be able to substitute
+/// a fake implementation in tests.
+/// Ids are taken as their concrete types, so that the trait can be used as `dyn FimfictionApi`.
+pub trait FimfictionApi {
+    fn blog_post(&self, id: BlogPostId) -> Result<BlogPostResponse, ApiError>;
+    fn bookshelf(&self, id: BookshelfId) -> Result<BookshelfResponse, ApiError>;
+    fn chapter(&self, id: ChapterId) -> Result<ChapterResponse, ApiError>;
+    fn group(&self, id: GroupId) -> Result<GroupResponse, ApiError>;
+    fn story(&self, id: StoryId) -> Result<StoryResponse, ApiError>;
+    /// Every chapter of a story, in order.
+    fn story_chapters(&self, id: StoryId) -> Result<ChaptersResponse, ApiError>;
+    fn story_tag(&self, id: StoryTagId) -> Result<StoryTagResponse, ApiError>;
+    fn user(&self, id: UserId) -> Result<UserResponse, ApiError>;
+
+    /// Check a story's `num_chapters` against the chapters listed for it,
+    /// returning the discrepancy, if any.
+    /// Only the chapters visible to the authorized user are counted.
+    fn verify_chapter_count(&self, story: &Story) -> Result<Option<ChapterCountMismatch>, ApiError> {
+        let actual = self.story_chapters(story.id)?.data.len();
+        Ok(ChapterCountMismatch::between(story, actual))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl FimfictionApi for Application {
+    fn blog_post(&self, id: BlogPostId) -> Result<BlogPostResponse, ApiError> {
+        Application::blog_post(self, id)
+    }
+    fn bookshelf(&self, id: BookshelfId) -> Result<BookshelfResponse, ApiError> {
+        Application::bookshelf(self, id)
+    }
+    fn chapter(&self, id: ChapterId) -> Result<ChapterResponse, ApiError> {
+        Application::chapter(self, id)
+    }
+    fn group(&self, id: GroupId) -> Result<GroupResponse, ApiError> {
+        Application::group(self, id)
+    }
+    fn story(&self, id: StoryId) -> Result<StoryResponse, ApiError> {
+        Application::story(self, id)
+    }
+    fn story_chapters(&self, id: StoryId) -> Result<ChaptersResponse, ApiError> {
+        Application::story_chapters(self, id)
+    }
+    fn story_tag(&self, id: StoryTagId) -> Result<StoryTagResponse, ApiError> {
+        Application::story_tag(self, id)
+    }
+    fn user(&self, id: UserId) -> Result<UserResponse, ApiError> {
+        Application::user(self, id)
+    }
+}
@@ -1,134 +1,923 @@
-use reqwest;
-use reqwest::{Client, header, RequestBuilder, Url};
+use chrono::{DateTime, Utc};
+use reqwest::blocking::{Client, Request, RequestBuilder, Response};
+use reqwest::header::{self, HeaderValue};
+use reqwest::{Method, StatusCode, Url};
 use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::Value;
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
 
-use resources::{BlogPost, Bookshelf, Chapter, Group, PrivateMessage, Resource, Story, User};
+use crate::auth::{require_scope, AuthError, Scope, TokenResponse};
+use crate::builder::{redact, ApplicationBuilder, Settings};
+use crate::cache::Cache;
+use crate::error::{check_status, parse_response, status_error, ApiError};
+use crate::query::{
+    join_ids, BlogPostInclude, BlogPostSortField, BookshelfInclude, ChapterInclude, Cursor, Filter,
+    GroupInclude, Include, Page, PrivateMessageInclude, Query, SearchQuery, Sort, StoryInclude,
+};
+use crate::resources::{
+    Avatar, BlogPost, BlogPostId, BookshelfId, ChapterId, CoverImage, CoverSize, Follow, GroupId,
+    PrivateMessageId, ResourceId, Sparse, Story, StoryId, StoryRelationships, StoryTag, StoryTagId,
+    User, UserId,
+};
+use crate::response::{
+    ApiResponse, BlogPostResponse, BlogPostsResponse, BookshelfResponse, BookshelvesResponse,
+    ChapterResponse, ChaptersResponse, CommentsResponse, Conditional, ETag, FollowResponse,
+    FollowersResponse, GroupResponse, GroupThreadsResponse, PrivateMessageResponse,
+    PrivateMessagesResponse, StoriesResponse, StoryResponse, StoryTagResponse, StoryTagsResponse,
+    TypedApiResponse, UserResponse, UsersResponse, WithETag, WithRaw, WithStatus,
+};
+use crate::write::{
+    relationship_members, ChapterPatch, Document, NewBookshelf, NewChapter, NewFollow,
+    NewPrivateMessage, NewVote, StoryPatch, StoryVote, MEDIA_TYPE,
+};
 
 /// Applications allow for the server to associate each request with some context
 /// (i.e. some application). https://www.fimfiction.net/developers/api/v2/docs/applications
-#[derive(Debug)]
+/// Clones are cheap, and share the same connection pool, rate limit and cache.
+#[derive(Clone)]
 pub struct Application {
     client: Client,
-    /// Header used to authorize any requests with fimfiction.
-    /// TODO: Can use header::Bearer?
-    auth_header: String,
+    /// Bearer token used to authorize any requests with fimfiction.
+    access_token: String,
+    /// `Authorization` header carrying `access_token`, built once rather than per request.
+    auth_header: HeaderValue,
+    /// Configuration this application was built with.
+    settings: Settings,
+    /// When the current access token stops being valid, if the token endpoint told us.
+    token_expires_at: Option<DateTime<Utc>>,
+    /// Scopes granted to us by the user, if known.
+    scopes: Option<Vec<Scope>>,
+    /// Whether the token was issued on behalf of a user, rather than just the application.
+    for_user: bool,
+    /// Id of that user, once /me has been retrieved. Shared with clones, which use the same token.
+    user_id: Arc<Mutex<Option<UserId>>>,
+    /// Responses cached on behalf of this application (and its clones) alone,
+    /// since some are specific to the user it's authorized as.
+    cache: Option<Cache>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ApiResponse {
-    pub data: Resource,
-    pub included: Vec<Resource>,
-    // NB: Url relative to fimfiction.net; cannot use Url type for that.
-    pub uri: String,
-    pub method: String,
-    pub debug: HashMap<String, Value>,
-}
-#[derive(Debug, Deserialize)]
-pub struct TypedApiResponse<T> {
-    pub data: T,
-    pub included: Vec<Resource>,
-    // NB: Url relative to fimfiction.net; cannot use Url type for that.
-    pub uri: String,
-    pub method: String,
-    pub debug: HashMap<String, Value>,
+/// Written by hand, so as not to leak the access token into logs.
+impl fmt::Debug for Application {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Application")
+            .field("client", &self.client)
+            .field("access_token", &"***")
+            .field("settings", &self.settings)
+            .field("token_expires_at", &self.token_expires_at)
+            .field("scopes", &self.scopes)
+            .field("for_user", &self.for_user)
+            .field("user_id", &self.user_id)
+            .field("cache", &self.cache)
+            .finish()
+    }
 }
-pub type BlogPostResponse = TypedApiResponse<BlogPost>;
-pub type BookshelfResponse = TypedApiResponse<Bookshelf>;
-pub type ChapterResponse = TypedApiResponse<Chapter>;
-//pub type FollowersResponse = TypedApiResponse<Vec<Follow>>;
-pub type GroupResponse = TypedApiResponse<Group>;
-pub type PrivateMessageResponse = TypedApiResponse<PrivateMessage>;
-pub type StoryResponse = TypedApiResponse<Story>;
-pub type UserResponse = TypedApiResponse<User>;
-
-
 
 impl Application {
     /// Authorize an application via client-specific credentials.
     /// See https://www.fimfiction.net/developers/api/v2/docs/oauth#authorisation-code
-    pub fn authorize_from_client_credentials(client_id: &str, client_secret: &str) -> Result<Self, reqwest::Error> {
-        let client = Client::new();
+    pub fn authorize_from_client_credentials(client_id: &str, client_secret: &str) -> Result<Self, AuthError> {
+        ApplicationBuilder::new().authorize_client_credentials(client_id, client_secret)
+    }
+    pub(crate) fn authorize_client_credentials_with(settings: Settings, client: Client, client_id: &str, client_secret: &str)
+        -> Result<Self, AuthError>
+    {
         let post_data: [(&str, &str); 3] = [
-            ("client_id", &client_id),
-            ("client_secret", &client_secret),
+            ("client_id", client_id),
+            ("client_secret", client_secret),
             ("grant_type", "client_credentials"),
         ];
-        #[derive(Debug, Deserialize)]
-        struct RespData {
-            access_token: String,
-            token_type: String,
-        }
-        let mut response = client.post(Self::endpoint("token"))
-            .header(header::USER_AGENT, Self::user_agent())
+        let response = client.post(settings.endpoint("token"))
+            .header(header::USER_AGENT, settings.user_agent.as_str())
             .form(&post_data)
             .send()?;
-        debug!("authorization response: {:?}", response);
-        let resp_data: RespData = response.json()?;
-        debug!("authorized: {:?}", resp_data);
-        Ok(Self{
-            client,
-            auth_header: resp_data.token_type + " " + &resp_data.access_token,
-        })
+        debug!("token request -> {}", response.status());
+        let status = response.status();
+        let resp_data = TokenResponse::parse(status, &response.text()?)?;
+        debug!("authorized; token expires in {:?} seconds", resp_data.expires_in);
+        Self::from_token(settings, client, &resp_data, false)
+    }
+    /// Authorize an application on behalf of a user, by exchanging the code
+    /// fimfiction passed to `redirect_uri` after the user visited `authorization_url`.
+    /// Returns the refresh token (if any) alongside the application, so it can be persisted.
+    /// See https://www.fimfiction.net/developers/api/v2/docs/oauth#authorisation-code
+    pub fn authorize_from_authorization_code(client_id: &str, client_secret: &str, code: &str, redirect_uri: &str)
+        -> Result<(Self, Option<String>), AuthError>
+    {
+        ApplicationBuilder::new().authorize_authorization_code(client_id, client_secret, code, redirect_uri)
+    }
+    pub(crate) fn authorize_authorization_code_with(settings: Settings, client: Client, client_id: &str, client_secret: &str,
+        code: &str, redirect_uri: &str) -> Result<(Self, Option<String>), AuthError>
+    {
+        let post_data: [(&str, &str); 5] = [
+            ("client_id", client_id),
+            ("client_secret", client_secret),
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+        ];
+        let response = client.post(settings.endpoint("token"))
+            .header(header::USER_AGENT, settings.user_agent.as_str())
+            .form(&post_data)
+            .send()?;
+        debug!("token request -> {}", response.status());
+        let status = response.status();
+        let resp_data = TokenResponse::parse(status, &response.text()?)?;
+        debug!("authorized; token expires in {:?} seconds", resp_data.expires_in);
+        Ok((Self::from_token(settings, client, &resp_data, true)?, resp_data.refresh_token))
+    }
+    /// Obtain a fresh access token using a refresh token from a previous authorization,
+    /// and use it for all subsequent requests. The token endpoint may also rotate the
+    /// refresh token, so the returned `TokenResponse` should be persisted in place of the old one.
+    /// See https://www.fimfiction.net/developers/api/v2/docs/oauth#refresh-tokens
+    pub fn refresh_token(&mut self, client_id: &str, client_secret: &str, refresh_token: &str)
+        -> Result<TokenResponse, AuthError>
+    {
+        let post_data: [(&str, &str); 4] = [
+            ("client_id", client_id),
+            ("client_secret", client_secret),
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ];
+        let response = self.client.post(self.settings.endpoint("token"))
+            .header(header::USER_AGENT, self.settings.user_agent.as_str())
+            .form(&post_data)
+            .send()?;
+        debug!("token refresh request -> {}", response.status());
+        let status = response.status();
+        let token = TokenResponse::parse(status, &response.text()?)?;
+        self.auth_header = token.authorization()?;
+        self.access_token = token.access_token.clone();
+        self.token_expires_at = token.expires_at();
+        if let Some(scopes) = token.scopes() {
+            self.scopes = Some(scopes);
+        }
+        Ok(token)
+    }
+    /// The current access token, e.g. for use with requests this crate doesn't support.
+    /// It's sent as a bearer token: `Authorization: Bearer <token>`.
+    pub fn access_token(&self) -> &str {
+        &self.access_token
+    }
+    /// Time at which the current access token expires, if known.
+    /// Not every grant type reports an expiry.
+    pub fn token_expires_at(&self) -> Option<DateTime<Utc>> {
+        self.token_expires_at
+    }
+    /// Forget every cached response, so that the next request for each is answered afresh.
+    /// The cache is shared with clones of this application, but not others built alongside it.
+    /// Does nothing unless caching was enabled via `ApplicationBuilder::cache_capacity`.
+    pub fn clear_cache(&self) {
+        if let Some(ref cache) = self.cache {
+            cache.clear();
+        }
+    }
+    /// Scopes the user granted to this application, if known.
+    /// See `ApplicationBuilder::scopes`.
+    pub fn scopes(&self) -> Option<&[Scope]> {
+        self.scopes.as_deref()
+    }
+    /// Add a story to a bookshelf (POST /bookshelves/:id/relationships/stories),
+    /// then retrieve the bookshelf as it is afterwards.
+    /// Adding a story which is already on the shelf has no effect.
+    /// Requires the `WriteBookshelves` scope.
+    pub fn add_story_to_bookshelf<B: Into<BookshelfId>, S: Into<StoryId>>(&self, shelf_id: B, story_id: S)
+        -> Result<BookshelfResponse, ApiError>
+    {
+        self.require_scope(Scope::WriteBookshelves)?;
+        let shelf_id = shelf_id.into();
+        let url = self.settings.endpoint(path!("bookshelves", shelf_id, "relationships", "stories"));
+        let body = relationship_members("story", &[story_id.into().as_u32()]);
+        // Safe to retry, since adding the same story twice is harmless.
+        self.send_empty(self.client.post(url).json(&body), true)?;
+        self.bookshelf(shelf_id)
     }
     /// Retrieve a blogpost by its id (/blog-posts/:id).
-    pub fn blog_post(&self, id: u32) -> Result<BlogPostResponse, reqwest::Error> {
-        self.do_request(
-            self.client.get(Self::endpoint(format!("blog-posts/{}", id)))
-        )
+    pub fn blog_post<I: Into<BlogPostId>>(&self, id: I) -> Result<BlogPostResponse, ApiError> {
+        self.blog_post_with(id, &[])
     }
-    /// Retrieve a bookshelf by its id (/blog-posts/:id).
-    pub fn bookshelf(&self, id: u32) -> Result<BookshelfResponse, reqwest::Error> {
-        self.do_request(
-            self.client.get(Self::endpoint(format!("bookshelves/{}", id)))
-        )
+    /// Same as `blog_post`, but also requests the given relationships be included in the response.
+    pub fn blog_post_with<I: Into<BlogPostId>>(&self, id: I, include: &[&str]) -> Result<BlogPostResponse, ApiError> {
+        self.get(path!("blog-posts", id.into()), Query::new().include(include))
     }
-    /// Retrieve a chapter by its id (/blog-posts/:id).
-    pub fn chapter(&self, id: u32) -> Result<ChapterResponse, reqwest::Error> {
-        self.do_request(
-            self.client.get(Self::endpoint(format!("chapters/{}", id)))
-        )
+    /// Same as `blog_post_with`, but the relationships are named by `BlogPostInclude` rather than strings.
+    pub fn blog_post_include<I: Into<BlogPostId>>(&self, id: I, include: &[BlogPostInclude]) -> Result<BlogPostResponse, ApiError> {
+        let paths: Vec<&str> = include.iter().map(Include::path).collect();
+        self.blog_post_with(id, &paths)
+    }
+    /// Retrieve the comments on a blog post (/blog-posts/:id/comments).
+    pub fn blog_post_comments<I: Into<BlogPostId>>(&self, post_id: I, page: Option<Page>) -> Result<CommentsResponse, ApiError> {
+        self.get(path!("blog-posts", post_id.into(), "comments"), Query::new().maybe_page(page))
+    }
+    /// Retrieve a bookshelf by its id (/bookshelves/:id).
+    pub fn bookshelf<I: Into<BookshelfId>>(&self, id: I) -> Result<BookshelfResponse, ApiError> {
+        self.bookshelf_with(id, &[])
+    }
+    /// Same as `bookshelf`, but also requests the given relationships be included in the response.
+    pub fn bookshelf_with<I: Into<BookshelfId>>(&self, id: I, include: &[&str]) -> Result<BookshelfResponse, ApiError> {
+        self.get(path!("bookshelves", id.into()), Query::new().include(include))
+    }
+    /// Same as `bookshelf_with`, but the relationships are named by `BookshelfInclude` rather than strings.
+    pub fn bookshelf_include<I: Into<BookshelfId>>(&self, id: I, include: &[BookshelfInclude]) -> Result<BookshelfResponse, ApiError> {
+        let paths: Vec<&str> = include.iter().map(Include::path).collect();
+        self.bookshelf_with(id, &paths)
+    }
+    /// Retrieve the stories on a bookshelf (/bookshelves/:id/stories).
+    pub fn bookshelf_stories<I: Into<BookshelfId>>(&self, shelf_id: I, page: Option<Page>) -> Result<StoriesResponse, ApiError> {
+        self.get(path!("bookshelves", shelf_id.into(), "stories"), Query::new().maybe_page(page))
+    }
+    /// Retrieve a chapter by its id (/chapters/:id).
+    pub fn chapter<I: Into<ChapterId>>(&self, id: I) -> Result<ChapterResponse, ApiError> {
+        self.chapter_with(id, &[])
+    }
+    /// Same as `chapter`, but also requests the given relationships be included in the response.
+    pub fn chapter_with<I: Into<ChapterId>>(&self, id: I, include: &[&str]) -> Result<ChapterResponse, ApiError> {
+        self.get(path!("chapters", id.into()), Query::new().include(include))
+    }
+    /// Same as `chapter_with`, but the relationships are named by `ChapterInclude` rather than strings.
+    pub fn chapter_include<I: Into<ChapterId>>(&self, id: I, include: &[ChapterInclude]) -> Result<ChapterResponse, ApiError> {
+        let paths: Vec<&str> = include.iter().map(Include::path).collect();
+        self.chapter_with(id, &paths)
+    }
+    /// Add a chapter to the end of a story (POST /stories/:id/chapters), returning the new chapter
+    /// and the status of the response (201 Created, normally).
+    /// Requires the `WriteStories` scope.
+    pub fn create_chapter<I: Into<StoryId>>(&self, story_id: I, chapter: NewChapter) -> Result<WithStatus<ChapterResponse>, ApiError> {
+        self.require_scope(Scope::WriteStories)?;
+        self.post(path!("stories", story_id.into(), "chapters"), &Document::create("chapter", chapter))
+    }
+    /// Create a bookshelf for the authorized user (POST /bookshelves), returning the new bookshelf
+    /// and the status of the response (201 Created, normally).
+    /// Requires the `WriteBookshelves` scope.
+    pub fn create_bookshelf(&self, shelf: NewBookshelf) -> Result<WithStatus<BookshelfResponse>, ApiError> {
+        self.require_scope(Scope::WriteBookshelves)?;
+        self.post("bookshelves".to_owned(), &Document::create("bookshelf", shelf))
+    }
+    /// Retrieve the bytes of the avatar image whose size is closest to `preferred_px`
+    /// (see `Avatar::closest`), alongside the size actually retrieved.
+    /// `None` if the avatar has no images at all.
+    pub fn download_avatar(&self, avatar: &Avatar, preferred_px: u32) -> Result<Option<(u32, Vec<u8>)>, ApiError> {
+        let size = match avatar.closest_size(preferred_px) {
+            Some(size) => size,
+            None => return Ok(None),
+        };
+        let bytes = self.download(&avatar.sizes[&size])?;
+        Ok(Some((size, bytes)))
+    }
+    /// Retrieve the bytes of a story's cover image, in the given size.
+    pub fn download_cover(&self, cover: &CoverImage, size: CoverSize) -> Result<Vec<u8>, ApiError> {
+        self.download(cover.url(size))
+    }
+    /// Retrieve whichever resource `id` refers to, e.g. one found in a relationship but not `included`.
+    /// Fails with `ApiError::UnknownResourceType` if there's no endpoint for its type.
+    pub fn fetch(&self, id: &ResourceId) -> Result<ApiResponse, ApiError> {
+        let path = id.path().ok_or_else(|| ApiError::UnknownResourceType(id.type_.clone()))?;
+        self.get(path, Query::new())
+    }
+    /// Make the authorized user follow another user (POST /users/:id/following),
+    /// returning the new follow and the status of the response.
+    /// Fimfiction doesn't document a scope for this, so none is checked here; if the token
+    /// doesn't allow it, the server's refusal is returned as `ApiError::Forbidden`
+    /// (whose `missing_scope` is set if the server names the scope it wanted).
+    /// Fails with `ApiError::NoUser` if the application was authorized via client credentials.
+    // NB: untested!
+    pub fn follow_user<I: Into<UserId>>(&self, user_id: I)
+        -> Result<WithStatus<FollowResponse>, ApiError>
+    {
+        let me = self.user_id()?;
+        let body = Document::create("follow", NewFollow {})
+            .relationship("following", "user", user_id.into().as_u32());
+        self.post(path!("users", me, "following"), &body)
+    }
+    /// GET the given endpoint (relative to the API root, e.g. "stories/1234?include=author"),
+    /// leaving the body of the response to the caller, e.g. to stream it.
+    /// The request is authorized and retried like any other, but bypasses the cache.
+    /// Only a successful response is returned; any other is turned into an `ApiError` as usual.
+    /// Fails with `ApiError::InvalidPath`, without sending anything, if `path` doesn't resolve to
+    /// somewhere under the API root (e.g. it's a URL of its own), lest the token be sent elsewhere.
+    pub fn get_raw(&self, path: &str) -> Result<Response, ApiError> {
+        let url = self.settings.endpoint_within(path)?;
+        let mut req = self.build(self.client.get(url))?;
+        // A 304 would leave the caller with no body at all.
+        req.headers_mut().remove(header::IF_MODIFIED_SINCE);
+        req.headers_mut().remove(header::IF_NONE_MATCH);
+        let resp = self.execute(req, true)?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.bytes()?;
+            return Err(status_error(status, &body));
+        }
+        Ok(resp)
     }
     /// Retrieve a group by its id (/groups/:id).
-    pub fn group(&self, id: u32) -> Result<GroupResponse, reqwest::Error> {
-        self.do_request(
-            self.client.get(Self::endpoint(format!("groups/{}", id)))
-        )
+    pub fn group<I: Into<GroupId>>(&self, id: I) -> Result<GroupResponse, ApiError> {
+        self.group_with(id, &[])
+    }
+    /// Same as `group`, but also requests the given relationships be included in the response.
+    pub fn group_with<I: Into<GroupId>>(&self, id: I, include: &[&str]) -> Result<GroupResponse, ApiError> {
+        self.get(path!("groups", id.into()), Query::new().include(include))
+    }
+    /// Same as `group_with`, but the relationships are named by `GroupInclude` rather than strings.
+    pub fn group_include<I: Into<GroupId>>(&self, id: I, include: &[GroupInclude]) -> Result<GroupResponse, ApiError> {
+        let paths: Vec<&str> = include.iter().map(Include::path).collect();
+        self.group_with(id, &paths)
+    }
+    /// Retrieve the members of a group (/groups/:id/members).
+    /// Large groups span many pages; see `group_members_iter`.
+    pub fn group_members<I: Into<GroupId>>(&self, group_id: I, page: Option<Page>) -> Result<UsersResponse, ApiError> {
+        self.get(path!("groups", group_id.into(), "members"), Query::new().maybe_page(page))
+    }
+    /// Same as `group_members`, but iterates over every member, across all pages.
+    pub fn group_members_iter<I: Into<GroupId>>(&self, group_id: I) -> ResourceStream<'_, User> {
+        let url = self.settings.endpoint_with_query(path!("groups", group_id.into(), "members"), &Query::new());
+        self.resources(url)
+    }
+    /// Retrieve the stories added to a group (/groups/:id/stories).
+    /// If `folder` is given, only the stories in that folder of the group are requested
+    /// (via `filter[folder]`). The API's support for this is undocumented: should it ignore
+    /// the filter, every story in the group is returned instead, since a story's folders
+    /// aren't part of the response and can't be checked here.
+    pub fn group_stories<I: Into<GroupId>>(&self, group_id: I, folder: Option<u32>, page: Option<Page>)
+        -> Result<StoriesResponse, ApiError>
+    {
+        let mut filter = Filter::new();
+        if let Some(folder) = folder {
+            filter = filter.param("folder", folder.to_string());
+        }
+        let query = Query::new().filter(&filter).maybe_page(page);
+        self.get(path!("groups", group_id.into(), "stories"), query)
+    }
+    /// Retrieve the discussion threads of a group (/groups/:id/threads).
+    /// Within the page, sticky threads are moved to the front; otherwise the API's order is kept.
+    pub fn group_threads<I: Into<GroupId>>(&self, group_id: I, page: Option<Page>) -> Result<GroupThreadsResponse, ApiError> {
+        let mut resp: GroupThreadsResponse = self.get(
+            path!("groups", group_id.into(), "threads"), Query::new().maybe_page(page)
+        )?;
+        resp.data.sort_by_key(|thread| !thread.attributes.sticky);
+        Ok(resp)
+    }
+    /// Whether the authorized user follows the given user.
+    /// Pages through the authorized user's follows (/users/:id/following) until it's found.
+    /// Fails with `ApiError::NoUser` if the application was authorized via client credentials.
+    pub fn is_following<I: Into<UserId>>(&self, user_id: I) -> Result<bool, ApiError> {
+        let user_id = user_id.into();
+        let me = self.user_id()?;
+        let url = self.settings.endpoint_with_query(path!("users", me, "following"), &Query::new());
+        for follow in self.resources::<Follow>(url) {
+            if follow?.relationships.map(|rel| rel.following.data.id) == Some(user_id.as_u32()) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+    /// Retrieve the user on whose behalf the application is authorized (/me).
+    /// Fails with `ApiError::NoUser` if it was authorized via client credentials.
+    pub fn me(&self) -> Result<UserResponse, ApiError> {
+        if !self.for_user {
+            return Err(ApiError::NoUser);
+        }
+        self.get("me".to_owned(), Query::new())
+    }
+    /// Id of the authorized user, retrieving it from /me the first time it's needed.
+    fn user_id(&self) -> Result<UserId, ApiError> {
+        if let Some(id) = *self.user_id.lock().unwrap() {
+            return Ok(id);
+        }
+        let id = self.me()?.data.id;
+        *self.user_id.lock().unwrap() = Some(id);
+        Ok(id)
+    }
+    /// Retrieve the `limit` tags with the most stories, most popular first.
+    /// Every page of tags is fetched, then ranked by `num_stories`.
+    pub fn popular_tags(&self, limit: u32) -> Result<Vec<StoryTag>, ApiError> {
+        let url = self.settings.endpoint_with_query("story-tags", &Query::new());
+        let mut tags = self.resources::<StoryTag>(url).collect::<Result<Vec<_>, _>>()?;
+        tags.sort_by_key(|tag| Reverse(tag.attributes.num_stories));
+        tags.truncate(limit as usize);
+        Ok(tags)
     }
     /// Retrieve a private message by its id (/private-messages/:id).
-    // NB: untested! Requires the read_pms scope.
-    pub fn private_message(&self, id: u32) -> Result<PrivateMessageResponse, reqwest::Error> {
-        self.do_request(
-            self.client.get(Self::endpoint(format!("private-messages/{}", id)))
-        )
+    /// Requires the `ReadPms` scope.
+    // NB: untested!
+    pub fn private_message<I: Into<PrivateMessageId>>(&self, id: I) -> Result<PrivateMessageResponse, ApiError> {
+        self.private_message_with(id, &[])
+    }
+    /// Same as `private_message`, but also requests the given relationships be included in the response.
+    pub fn private_message_with<I: Into<PrivateMessageId>>(&self, id: I, include: &[&str]) -> Result<PrivateMessageResponse, ApiError> {
+        self.require_scope(Scope::ReadPms)?;
+        self.get(path!("private-messages", id.into()), Query::new().include(include))
+    }
+    /// Same as `private_message_with`, but the relationships are named by `PrivateMessageInclude` rather than strings.
+    pub fn private_message_include<I: Into<PrivateMessageId>>(&self, id: I, include: &[PrivateMessageInclude]) -> Result<PrivateMessageResponse, ApiError> {
+        let paths: Vec<&str> = include.iter().map(Include::path).collect();
+        self.private_message_with(id, &paths)
+    }
+    /// Retrieve the authorized user's private messages (/private-messages).
+    /// Requires the `ReadPms` scope.
+    pub fn private_messages(&self, page: Option<Page>) -> Result<PrivateMessagesResponse, ApiError> {
+        self.require_scope(Scope::ReadPms)?;
+        self.get("private-messages".to_owned(), Query::new().maybe_page(page))
+    }
+    /// Like or dislike a story as the authorized user (POST /stories/:id/vote),
+    /// or withdraw their vote (DELETE /stories/:id/vote),
+    /// then return the story's `(num_likes, num_dislikes)` as they are afterwards.
+    /// Casting the same vote again has no effect, and a new vote replaces the old one.
+    /// Requires the `WriteStories` scope.
+    // NB: untested!
+    pub fn rate_story<I: Into<StoryId>>(&self, story_id: I, vote: StoryVote)
+        -> Result<(u32, u32), ApiError>
+    {
+        self.require_scope(Scope::WriteStories)?;
+        let story_id = story_id.into();
+        let url = self.settings.endpoint(path!("stories", story_id, "vote"));
+        // Both are safe to retry, since voting is idempotent.
+        let req = match vote {
+            StoryVote::Like => self.client.post(url).json(&Document::create("vote", NewVote { vote: "like" })),
+            StoryVote::Dislike => self.client.post(url).json(&Document::create("vote", NewVote { vote: "dislike" })),
+            StoryVote::None => self.client.delete(url),
+        };
+        self.send_empty(req, true)?;
+        let story = self.story(story_id)?.data.attributes;
+        Ok((story.num_likes, story.num_dislikes))
+    }
+    /// Counterpart to `add_story_to_bookshelf` (DELETE /bookshelves/:id/relationships/stories).
+    /// Removing a story which isn't on the shelf has no effect.
+    /// Requires the `WriteBookshelves` scope.
+    pub fn remove_story_from_bookshelf<B: Into<BookshelfId>, S: Into<StoryId>>(&self, shelf_id: B, story_id: S)
+        -> Result<BookshelfResponse, ApiError>
+    {
+        self.require_scope(Scope::WriteBookshelves)?;
+        let shelf_id = shelf_id.into();
+        let url = self.settings.endpoint(path!("bookshelves", shelf_id, "relationships", "stories"));
+        let body = relationship_members("story", &[story_id.into().as_u32()]);
+        self.send_empty(self.client.delete(url).json(&body), true)?;
+        self.bookshelf(shelf_id)
+    }
+    /// Search for stories matching `query` (/stories).
+    pub fn search_stories(&self, query: &SearchQuery) -> Result<StoriesResponse, ApiError> {
+        self.get("stories".to_owned(), query.to_query())
+    }
+    /// Same as `search_stories`, but iterates over every matching story, across all pages.
+    /// The page size is taken from `query` (if set), but its page number is ignored.
+    pub fn search_stories_iter(&self, query: &SearchQuery) -> ResourceStream<'_, Story> {
+        self.resources(self.settings.endpoint_with_query("stories", &query.to_first_page_query()))
+    }
+    /// Same as `search_stories`, but only the attributes named in `fields` are returned
+    /// (e.g. `{"story": ["title", "num_words"]}`), and parsed into `A`.
+    pub fn search_stories_sparse<A: DeserializeOwned + Debug>(&self, query: &SearchQuery, fields: &HashMap<&str, Vec<&str>>)
+        -> Result<TypedApiResponse<Vec<Sparse<StoryId, A>>>, ApiError>
+    {
+        self.get("stories".to_owned(), query.to_query().fields(fields))
+    }
+    /// Send a private message to a user (POST /private-messages), returning the sent message
+    /// and the status of the response.
+    /// `content` is BBCode. Requires the `WritePms` scope.
+    pub fn send_private_message<I: Into<UserId>>(&self, to_user_id: I, subject: &str, content: &str)
+        -> Result<WithStatus<PrivateMessageResponse>, ApiError>
+    {
+        self.require_scope(Scope::WritePms)?;
+        let body = Document::create("private_message", NewPrivateMessage { subject, content })
+            .relationship("receiver", "user", to_user_id.into().as_u32());
+        self.post("private-messages".to_owned(), &body)
+    }
+    /// Retrieve several stories at once (/stories?filter[id]=...).
+    /// Ids which don't correspond to a (visible) story are simply absent from the result.
+    /// The whole batch is requested as a single page, so `ids` should be no longer than the API's maximum page size.
+    /// No request is made if `ids` is empty.
+    pub fn stories(&self, ids: &[StoryId]) -> Result<StoriesResponse, ApiError> {
+        if ids.is_empty() {
+            return Ok(TypedApiResponse::empty("/api/v2/stories".to_owned()));
+        }
+        let page = Page { number: 1, size: ids.len() as u32 };
+        self.get("stories".to_owned(), Query::new().param("filter[id]", join_ids(ids)).page(page))
+    }
+    /// Retrieve the stories having every tag in `include_tags`, and none in `exclude_tags`.
+    /// Shorthand for `search_stories` with just those criteria.
+    pub fn stories_with_tags(&self, include_tags: &[StoryTagId], exclude_tags: &[StoryTagId], page: Option<Page>)
+        -> Result<StoriesResponse, ApiError>
+    {
+        let mut query = SearchQuery::new().tags(include_tags).exclude_tags(exclude_tags);
+        if let Some(page) = page {
+            query = query.page(page);
+        }
+        self.search_stories(&query)
     }
     /// Retrieve a story by its id (/stories/:id).
-    pub fn story(&self, id: u32) -> Result<StoryResponse, reqwest::Error> {
-        self.do_request(
-            self.client.get(Self::endpoint(format!("stories/{}", id)))
-        )
+    pub fn story<I: Into<StoryId>>(&self, id: I) -> Result<StoryResponse, ApiError> {
+        self.story_with(id, &[])
+    }
+    /// Retrieve the story at a fimfiction URL, like "https://www.fimfiction.net/story/12345/some-title"
+    /// or "https://fimfiction.net/s/12345".
+    pub fn story_from_url(&self, url: &str) -> Result<StoryResponse, ApiError> {
+        let id = StoryId::from_url(url).ok_or_else(|| ApiError::InvalidStoryUrl(url.to_owned()))?;
+        self.story(id)
+    }
+    /// Same as `story`, but also requests the given relationships be included in the response.
+    pub fn story_with<I: Into<StoryId>>(&self, id: I, include: &[&str]) -> Result<StoryResponse, ApiError> {
+        self.get(path!("stories", id.into()), Query::new().include(include))
+    }
+    /// Same as `story_with`, but the relationships are named by `StoryInclude` rather than strings.
+    pub fn story_include<I: Into<StoryId>>(&self, id: I, include: &[StoryInclude]) -> Result<StoryResponse, ApiError> {
+        let paths: Vec<&str> = include.iter().map(Include::path).collect();
+        self.story_with(id, &paths)
+    }
+    /// Same as `story`, but also returns the raw JSON of the response.
+    pub fn story_raw<I: Into<StoryId>>(&self, id: I) -> Result<WithRaw<StoryResponse>, ApiError> {
+        let raw: Value = self.get(path!("stories", id.into()), Query::new())?;
+        WithRaw::parse(raw)
+    }
+    /// Same as `story`, but if the story is still the version identified by `etag`,
+    /// returns `Conditional::NotModified` instead of the (unchanged) story.
+    /// Pass `None` to fetch the story unconditionally, e.g. to obtain its first ETag.
+    pub fn story_if_changed<I: Into<StoryId>>(&self, id: I, etag: Option<&ETag>)
+        -> Result<Conditional<StoryResponse>, ApiError>
+    {
+        self.get_conditional(path!("stories", id.into()), Query::new(), etag)
+    }
+    /// Retrieve the prequel of a story, or `None` if it has none (or fimfiction doesn't say).
+    pub fn story_prequel<I: Into<StoryId>>(&self, id: I) -> Result<Option<StoryResponse>, ApiError> {
+        let story = self.story(id)?;
+        let prequel = story.data.relationships.as_ref().and_then(StoryRelationships::prequel);
+        match prequel {
+            Some(prequel) => self.story(prequel.id).map(Some),
+            None => Ok(None),
+        }
+    }
+    /// Retrieve a story, but only if it was updated (i.e. had a chapter added) after `since`.
+    /// Handy for polling for new chapters.
+    pub fn story_updated_since<I: Into<StoryId>>(&self, id: I, since: DateTime<Utc>) -> Result<Option<Story>, ApiError> {
+        let resp = self.story(id)?;
+        match resp.data.attributes.date_updated {
+            Some(updated) if updated > since => Ok(Some(resp.data)),
+            _ => Ok(None),
+        }
+    }
+    /// Same as `story`, but only the attributes named in `fields` are returned
+    /// (e.g. `{"story": ["title", "num_words"]}`), and parsed into `A`.
+    pub fn story_sparse<A: DeserializeOwned + Debug, I: Into<StoryId>>(&self, id: I, fields: &HashMap<&str, Vec<&str>>)
+        -> Result<TypedApiResponse<Sparse<StoryId, A>>, ApiError>
+    {
+        self.get(path!("stories", id.into()), Query::new().fields(fields))
+    }
+    /// Retrieve all chapters of a story (/stories/:id/chapters), ordered by `chapter_number`.
+    pub fn story_chapters<I: Into<StoryId>>(&self, story_id: I) -> Result<ChaptersResponse, ApiError> {
+        let mut resp: ChaptersResponse = self.get(path!("stories", story_id.into(), "chapters"), Query::new())?;
+        resp.data.sort_by_key(|chapter| chapter.attributes.chapter_number);
+        Ok(resp)
+    }
+    /// Retrieve the comments on a story (/stories/:id/comments).
+    pub fn story_comments<I: Into<StoryId>>(&self, story_id: I, page: Option<Page>) -> Result<CommentsResponse, ApiError> {
+        self.get(path!("stories", story_id.into(), "comments"), Query::new().maybe_page(page))
+    }
+    /// Retrieve a story tag by its id (/story-tags/:id).
+    pub fn story_tag<I: Into<StoryTagId>>(&self, id: I) -> Result<StoryTagResponse, ApiError> {
+        self.get(path!("story-tags", id.into()), Query::new())
+    }
+    /// Retrieve every tag stories may be tagged with (/story-tags).
+    pub fn story_tags(&self) -> Result<StoryTagsResponse, ApiError> {
+        self.get("story-tags".to_owned(), Query::new())
+    }
+    /// Counterpart to `follow_user` (DELETE /users/:id/following/:id).
+    /// Unfollowing a user who isn't followed has no effect.
+    /// As with `follow_user`, no scope is checked here, since fimfiction doesn't document one;
+    /// a token which doesn't allow it gets `ApiError::Forbidden`, naming the scope if the server does.
+    // NB: untested!
+    pub fn unfollow_user<I: Into<UserId>>(&self, user_id: I) -> Result<(), ApiError> {
+        let me = self.user_id()?;
+        let url = self.settings.endpoint(path!("users", me, "following", user_id.into()));
+        // Safe to retry, since unfollowing twice is harmless.
+        self.send_empty(self.client.delete(url), true)
+    }
+    /// Change some of a chapter's attributes (PATCH /chapters/:id), returning the updated chapter
+    /// and the status of the response.
+    /// Requires the `WriteStories` scope.
+    pub fn update_chapter<I: Into<ChapterId>>(&self, id: I, patch: ChapterPatch) -> Result<WithStatus<ChapterResponse>, ApiError> {
+        self.require_scope(Scope::WriteStories)?;
+        let id = id.into();
+        self.patch(path!("chapters", id), &Document::update("chapter", id, patch))
+    }
+    /// Change some of a story's attributes (PATCH /stories/:id), returning the updated story
+    /// and the status of the response.
+    /// Requires the `WriteStories` scope.
+    pub fn update_story<I: Into<StoryId>>(&self, id: I, patch: StoryPatch) -> Result<WithStatus<StoryResponse>, ApiError> {
+        self.require_scope(Scope::WriteStories)?;
+        let id = id.into();
+        self.patch(path!("stories", id), &Document::update("story", id, patch))
     }
     /// Retrieve a user by its id (/users/:id).
-    pub fn user(&self, id: u32) -> Result<UserResponse, reqwest::Error> {
+    pub fn user<I: Into<UserId>>(&self, id: I) -> Result<UserResponse, ApiError> {
+        self.user_with(id, &[])
+    }
+    /// Same as `user`, but also requests the given relationships be included in the response.
+    pub fn user_with<I: Into<UserId>>(&self, id: I, include: &[&str]) -> Result<UserResponse, ApiError> {
+        self.get(path!("users", id.into()), Query::new().include(include))
+    }
+    /// Retrieve all bookshelves of a user (/users/:id/bookshelves).
+    /// Private and unlisted shelves are only returned if the application is authorized to see them.
+    pub fn user_bookshelves<I: Into<UserId>>(&self, user_id: I) -> Result<BookshelvesResponse, ApiError> {
+        self.get(path!("users", user_id.into(), "bookshelves"), Query::new())
+    }
+    /// Retrieve the blog posts written by a user (/users/:id/blog-posts), newest first.
+    pub fn user_blog_posts<I: Into<UserId>>(&self, user_id: I, page: Option<Page>) -> Result<BlogPostsResponse, ApiError> {
+        let query = Query::new()
+            .maybe_page(page)
+            .sort(&[Sort::descending(BlogPostSortField::DatePosted)]);
+        self.get(path!("users", user_id.into(), "blog-posts"), query)
+    }
+    /// Same as `user_blog_posts`, but iterates over every blog post, across all pages.
+    pub fn user_blog_posts_iter<I: Into<UserId>>(&self, user_id: I) -> ResourceStream<'_, BlogPost> {
+        let query = Query::new().sort(&[Sort::descending(BlogPostSortField::DatePosted)]);
+        let url = self.settings.endpoint_with_query(path!("users", user_id.into(), "blog-posts"), &query);
+        self.resources(url)
+    }
+    /// Retrieve the users following a user (/users/:id/followers).
+    pub fn user_followers<I: Into<UserId>>(&self, user_id: I, page: Option<Page>) -> Result<FollowersResponse, ApiError> {
+        self.get(path!("users", user_id.into(), "followers"), Query::new().maybe_page(page))
+    }
+    /// Same as `user_followers`, but continuing from `cursor` (from a previous page's
+    /// `next_cursor`), or from the start if it's `None`.
+    /// Unlike numbered pages, following the cursors neither repeats nor skips followers
+    /// when the list changes in between requests, if fimfiction paginates it by cursor.
+    pub fn user_followers_from<I: Into<UserId>>(&self, user_id: I, cursor: Option<&Cursor>)
+        -> Result<FollowersResponse, ApiError>
+    {
+        self.get(path!("users", user_id.into(), "followers"), Query::new().cursor(cursor))
+    }
+    /// Retrieve the users a user is following (/users/:id/following).
+    pub fn user_following<I: Into<UserId>>(&self, user_id: I, page: Option<Page>) -> Result<FollowersResponse, ApiError> {
+        self.get(path!("users", user_id.into(), "following"), Query::new().maybe_page(page))
+    }
+    /// Same as `user_following`, but continuing from `cursor`; see `user_followers_from`.
+    pub fn user_following_from<I: Into<UserId>>(&self, user_id: I, cursor: Option<&Cursor>)
+        -> Result<FollowersResponse, ApiError>
+    {
+        self.get(path!("users", user_id.into(), "following"), Query::new().cursor(cursor))
+    }
+    /// Retrieve the stories written by a user (/users/:id/stories).
+    pub fn user_stories<I: Into<UserId>>(&self, user_id: I, page: Option<Page>) -> Result<StoriesResponse, ApiError> {
+        self.get(path!("users", user_id.into(), "stories"), Query::new().maybe_page(page))
+    }
+    /// Same as `user_stories`, but iterates over every story, across all pages.
+    pub fn user_stories_iter<I: Into<UserId>>(&self, user_id: I) -> ResourceStream<'_, Story> {
+        let url = self.settings.endpoint_with_query(path!("users", user_id.into(), "stories"), &Query::new());
+        self.resources(url)
+    }
+    /// Check that the access token is (still) accepted, by making a cheap request with it.
+    /// Fails with `ApiError::Unauthorized` if it isn't, or with whatever else went wrong.
+    /// Says nothing about which scopes were granted.
+    pub fn verify_auth(&self) -> Result<(), ApiError> {
+        let query = Query::new().page(Page { number: 1, size: 1 });
+        self.get::<StoriesResponse>("stories".to_owned(), query)?;
+        Ok(())
+    }
+    /// Retrieve the page following `page`, or `None` if `page` is the last one.
+    /// Fails with `ApiError::InvalidPath` if its `next` link leads somewhere other than the API.
+    pub fn next_page<T: DeserializeOwned>(&self, page: &TypedApiResponse<Vec<T>>)
+        -> Result<Option<TypedApiResponse<Vec<T>>>, ApiError>
+    {
+        match page.links.next {
+            Some(ref next) => self.follow_link(next).map(Some),
+            None => Ok(None),
+        }
+    }
+    /// Iterate over `first` and every page after it, fetching each one as it's reached.
+    pub fn pages<T: DeserializeOwned>(&self, first: TypedApiResponse<Vec<T>>) -> Pages<'_, T> {
+        Pages {
+            app: self,
+            pending: Some(first),
+            next: None,
+        }
+    }
+    /// Iterate over every resource in a collection, starting at `url`,
+    /// fetching each page as the previous one is exhausted.
+    fn resources<T: DeserializeOwned>(&self, url: Url) -> ResourceStream<'_, T> {
+        ResourceStream {
+            pages: Pages {
+                app: self,
+                pending: None,
+                next: Some(url),
+            },
+            current: Vec::new().into_iter(),
+        }
+    }
+
+    fn from_token(settings: Settings, client: Client, token: &TokenResponse, for_user: bool) -> Result<Self, AuthError> {
+        Ok(Self {
+            client,
+            access_token: token.access_token.clone(),
+            auth_header: token.authorization()?,
+            token_expires_at: token.expires_at(),
+            scopes: settings.granted_scopes(token),
+            for_user,
+            user_id: Arc::new(Mutex::new(None)),
+            cache: settings.cache_capacity.map(Cache::new),
+            settings,
+        })
+    }
+    /// Fail with `ApiError::MissingScope` if `scope` is known not to have been granted.
+    fn require_scope(&self, scope: Scope) -> Result<(), ApiError> {
+        require_scope(self.scopes(), scope)
+    }
+    /// GET the given endpoint, with the given query string.
+    fn get<T: DeserializeOwned>(&self, path: String, query: Query) -> Result<T, ApiError> {
         self.do_request(
-            self.client.get(Self::endpoint(format!("users/{}", id)))
+            self.client.get(self.settings.endpoint(path)).query(query.pairs())
         )
     }
-
-    /// Build the full URL to the given endpoint
-    fn endpoint<T: AsRef<str>>(tail: T) -> Url {
-        Url::parse("https://www.fimfiction.net/api/v2/").unwrap().join(tail.as_ref()).unwrap()
+    /// POST `body` to the given endpoint. Never retried, lest the resource be created twice.
+    fn post<T: DeserializeOwned, A: Serialize>(&self, path: String, body: &Document<A>)
+        -> Result<WithStatus<T>, ApiError>
+    {
+        self.send_with_status(self.client.post(self.settings.endpoint(path)).json(body), false)
     }
-    fn do_request<T: DeserializeOwned>(&self, req: RequestBuilder) -> Result<T, reqwest::Error> {
-        let mut resp = req.header(header::AUTHORIZATION, self.auth_header.clone())
-            .header(header::USER_AGENT, Self::user_agent())
-            .send()?;
-        debug!("do_request response: {:?}", resp);
-        //println!("resp.text: {}", resp.text().unwrap());
-        resp.json()
+    /// PATCH the given endpoint with `body`.
+    /// Retried like a GET, since applying the same changes twice has no further effect.
+    fn patch<T: DeserializeOwned, A: Serialize>(&self, path: String, body: &Document<A>)
+        -> Result<WithStatus<T>, ApiError>
+    {
+        self.send_with_status(self.client.patch(self.settings.endpoint(path)).json(body), true)
     }
-    fn user_agent() -> &'static str {
-        "rust-fimfiction-api"
+    /// GET the given endpoint, unless it's still the version identified by `etag`.
+    /// Bypasses the cache, if any, since the caller is keeping track of versions themselves.
+    fn get_conditional<T: DeserializeOwned>(&self, path: String, query: Query, etag: Option<&ETag>)
+        -> Result<Conditional<T>, ApiError>
+    {
+        let mut req = self.build(self.client.get(self.settings.endpoint(path)).query(query.pairs()))?;
+        req.headers_mut().remove(header::IF_MODIFIED_SINCE);
+        req.headers_mut().remove(header::IF_NONE_MATCH);
+        // An ETag which can't be sent in a header can't match anything, either.
+        if let Some(value) = etag.and_then(|etag| HeaderValue::from_str(etag.as_str()).ok()) {
+            req.headers_mut().insert(header::IF_NONE_MATCH, value);
+        }
+        let resp = self.execute(req, true)?;
+        let status = resp.status();
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(Conditional::NotModified);
+        }
+        let etag = ETag::from_headers(resp.headers());
+        let value = parse_response(status, &resp.bytes()?)?;
+        Ok(Conditional::Modified(WithETag { value, etag }))
+    }
+    /// Send the request, retrying it as configured if it fails.
+    /// Only use for requests which are safe to repeat.
+    fn do_request<T: DeserializeOwned>(&self, req: RequestBuilder) -> Result<T, ApiError> {
+        self.send(req, true)
+    }
+    /// GET a page the server linked to (e.g. `links.next`). Fails with `ApiError::InvalidPath`,
+    /// without sending anything, unless it's under the API root, lest the token be sent elsewhere.
+    fn follow_link<T: DeserializeOwned>(&self, link: &Url) -> Result<T, ApiError> {
+        let url = self.settings.endpoint_within(link.as_str())?;
+        self.do_request(self.client.get(url))
+    }
+    /// Send the request, retrying it only if `retry` is set.
+    /// Requests which aren't safe to repeat (e.g. POSTs) must pass `false`.
+    fn send<T: DeserializeOwned>(&self, req: RequestBuilder, retry: bool) -> Result<T, ApiError> {
+        Ok(self.send_with_status(req, retry)?.value)
+    }
+    /// Same as `send`, but also returns the status of the (successful) response.
+    fn send_with_status<T: DeserializeOwned>(&self, req: RequestBuilder, retry: bool)
+        -> Result<WithStatus<T>, ApiError>
+    {
+        let req = self.build(req)?;
+        let cache = self.cache.as_ref().filter(|_| req.method() == Method::GET);
+        let url = req.url().clone();
+        let resp = self.execute(req, retry)?;
+        let status = resp.status();
+        let headers = resp.headers().clone();
+        let body = resp.bytes()?.to_vec();
+        trace!("response body: {}", String::from_utf8_lossy(&body));
+        let (status, body) = match cache {
+            Some(cache) => cache.resolve(&url, status, &headers, body),
+            None => (status, body),
+        };
+        let value = parse_response(status, &body)?;
+        Ok(WithStatus { value, status })
+    }
+    /// Same as `send`, for requests whose successful response has no body worth parsing.
+    fn send_empty(&self, req: RequestBuilder, retry: bool) -> Result<(), ApiError> {
+        let resp = self.execute(self.build(req)?, retry)?;
+        let status = resp.status();
+        check_status(status, &resp.bytes()?)
+    }
+    /// Retrieve the file at `url`, e.g. an image on fimfiction's CDN, retrying as configured.
+    /// The request isn't authorized, so that the access token is only ever sent to the API.
+    fn download(&self, url: &Url) -> Result<Vec<u8>, ApiError> {
+        let mut req = self.client.get(url.clone())
+            .header(header::USER_AGENT, self.settings.user_agent.as_str());
+        if let Some(timeout) = self.settings.timeout {
+            req = req.timeout(timeout);
+        }
+        let resp = self.execute(req.build()?, true)?;
+        let status = resp.status();
+        let body = resp.bytes()?.to_vec();
+        check_status(status, &body)?;
+        Ok(body)
+    }
+    /// Finish a request by authorizing it, and making a GET conditional on any cached copy.
+    fn build(&self, req: RequestBuilder) -> Result<Request, ApiError> {
+        let mut req = req.header(header::AUTHORIZATION, self.auth_header.clone())
+            .header(header::USER_AGENT, self.settings.user_agent.as_str());
+        if let Some(timeout) = self.settings.timeout {
+            req = req.timeout(timeout);
+        }
+        let mut req = req.build()?;
+        self.settings.apply_request_debug(req.url_mut());
+        // Content negotiation per https://jsonapi.org/format/#content-negotiation-clients
+        req.headers_mut().insert(header::ACCEPT, HeaderValue::from_static(MEDIA_TYPE));
+        if req.body().is_some() {
+            req.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static(MEDIA_TYPE));
+        }
+        if let Some(ref cache) = self.cache {
+            if req.method() == Method::GET {
+                let url = req.url().clone();
+                cache.add_validators(&url, req.headers_mut());
+            }
+        }
+        Ok(req)
+    }
+    /// Send the request (retrying if `retry` is set), without interpreting the response.
+    fn execute(&self, mut req: Request, retry: bool) -> Result<Response, ApiError> {
+        let (method, url) = (req.method().clone(), redact(req.url()));
+        let mut retries = 0;
+        let resp = loop {
+            // Requests with streaming bodies can't be cloned, and hence can't be retried.
+            let copy = if retry { req.try_clone() } else { None };
+            if let Some(delay) = self.settings.throttle() {
+                debug!("rate limited; waiting {:?}", delay);
+                thread::sleep(delay);
+            }
+            let started = Instant::now();
+            let resp = self.client.execute(req)?;
+            debug!("{} {} -> {} in {:?}", method, url, resp.status(), started.elapsed());
+            let delay = self.settings.retry_delay(resp.status(), resp.headers(), retries);
+            match (copy, delay) {
+                (Some(copy), Some(delay)) => {
+                    debug!("retrying after {:?}", delay);
+                    thread::sleep(delay);
+                    req = copy;
+                    retries += 1;
+                }
+                _ => break resp,
+            }
+        };
+        Ok(resp)
+    }
+}
+
+/// Iterator over successive pages of a collection, created by `Application::pages`.
+/// Stops after the last page, or after the first error.
+#[derive(Debug)]
+pub struct Pages<'a, T> {
+    app: &'a Application,
+    /// Page already retrieved, but not yet yielded.
+    pending: Option<TypedApiResponse<Vec<T>>>,
+    next: Option<Url>,
+}
+
+impl<'a, T: DeserializeOwned> Iterator for Pages<'a, T> {
+    type Item = Result<TypedApiResponse<Vec<T>>, ApiError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let page = match self.pending.take() {
+            Some(page) => page,
+            None => {
+                let url = self.next.take()?;
+                match self.app.follow_link(&url) {
+                    Ok(page) => page,
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+        };
+        self.next = page.links.next.clone();
+        Some(Ok(page))
+    }
+}
+
+/// Iterator over the individual resources of a collection, spanning as many pages as it takes.
+/// Created by e.g. `Application::user_stories_iter`.
+/// Stops after the last resource, or after the first error.
+#[derive(Debug)]
+pub struct ResourceStream<'a, T> {
+    pages: Pages<'a, T>,
+    /// Remainder of the most recently fetched page.
+    current: std::vec::IntoIter<T>,
+}
+
+impl<'a, T: DeserializeOwned> Iterator for ResourceStream<'a, T> {
+    type Item = Result<T, ApiError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(resource) = self.current.next() {
+                return Some(Ok(resource));
+            }
+            match self.pages.next()? {
+                Ok(page) => self.current = page.data.into_iter(),
+                Err(err) => return Some(Err(err)),
+            }
+        }
     }
 }
@@ -0,0 +1,236 @@
+//! OAuth helpers shared by `Application` and `AsyncApplication`.
+//! See https://www.fimfiction.net/developers/api/v2/docs/oauth
+#[cfg(any(feature = "blocking", feature = "async"))]
+use chrono::{DateTime, Duration, Utc};
+#[cfg(any(feature = "blocking", feature = "async"))]
+use reqwest::header::HeaderValue;
+use reqwest::header::InvalidHeaderValue;
+use reqwest::{StatusCode, Url};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+use crate::error::ApiError;
+
+/// Permission which a user may grant to an application.
+/// See https://www.fimfiction.net/developers/api/v2/docs/oauth#scopes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    ReadSensitiveUserData,
+    ReadStories,
+    WriteStories,
+    ReadBookshelves,
+    WriteBookshelves,
+    ReadPms,
+    WritePms,
+    WriteBlogPosts,
+}
+
+/// Body of a successful response from the token endpoint.
+/// Its `Debug` output omits the tokens themselves.
+#[derive(Clone, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    /// Not returned by the client credentials grant.
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Number of seconds (from the time of the response) for which the access token is valid.
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+    /// Space-separated list of the scopes which were granted, if the server reports them.
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+/// Failure to obtain a token from the token endpoint.
+#[derive(Debug)]
+pub enum AuthError {
+    /// The request could not be sent, or its body could not be read.
+    Http(reqwest::Error),
+    /// The server accepted the request, but replied with something that isn't a token.
+    Json(serde_json::Error),
+    /// The access token contains characters which can't be sent in a header.
+    InvalidToken(InvalidHeaderValue),
+    /// The server refused to issue a token, e.g. because the refresh token was revoked or expired.
+    /// `error` and `description` are the OAuth `error` and `error_description` fields, if present.
+    Rejected {
+        status: StatusCode,
+        error: Option<String>,
+        description: Option<String>,
+    },
+}
+
+impl fmt::Debug for TokenResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TokenResponse")
+            .field("access_token", &"***")
+            .field("token_type", &self.token_type)
+            .field("refresh_token", &self.refresh_token.as_ref().map(|_| "***"))
+            .field("expires_in", &self.expires_in)
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
+impl Scope {
+    /// Every scope, in the order fimfiction's documentation lists them.
+    pub const ALL: [Scope; 8] = [
+        Scope::ReadSensitiveUserData,
+        Scope::ReadStories,
+        Scope::WriteStories,
+        Scope::ReadBookshelves,
+        Scope::WriteBookshelves,
+        Scope::ReadPms,
+        Scope::WritePms,
+        Scope::WriteBlogPosts,
+    ];
+    /// Name by which fimfiction refers to this scope.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::ReadSensitiveUserData => "read_sensitive_user_data",
+            Scope::ReadStories => "read_stories",
+            Scope::WriteStories => "write_stories",
+            Scope::ReadBookshelves => "read_bookshelves",
+            Scope::WriteBookshelves => "write_bookshelves",
+            Scope::ReadPms => "read_pms",
+            Scope::WritePms => "write_pms",
+            Scope::WriteBlogPosts => "write_blog_posts",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returned when parsing the name of a scope this crate doesn't know about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownScope(pub String);
+
+impl FromStr for Scope {
+    type Err = UnknownScope;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Scope::ALL.iter().copied()
+            .find(|scope| scope.as_str() == s)
+            .ok_or_else(|| UnknownScope(s.to_owned()))
+    }
+}
+
+impl fmt::Display for UnknownScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown scope: {}", self.0)
+    }
+}
+
+impl Error for UnknownScope {}
+
+/// Fail early with `ApiError::MissingScope` if `needed` is known not to have been granted.
+/// If `granted` is `None` (the scopes are unknown), let the server decide.
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn require_scope(granted: Option<&[Scope]>, needed: Scope) -> Result<(), ApiError> {
+    match granted {
+        Some(granted) if !granted.contains(&needed) => Err(ApiError::MissingScope(needed)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl TokenResponse {
+    /// Value of the `Authorization` header with which to send subsequent requests.
+    /// Marked sensitive, so that it's omitted from debug output.
+    pub(crate) fn authorization(&self) -> Result<HeaderValue, AuthError> {
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", self.access_token))
+            .map_err(AuthError::InvalidToken)?;
+        value.set_sensitive(true);
+        Ok(value)
+    }
+    /// Absolute time at which the access token expires, assuming it was issued just now.
+    pub(crate) fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.expires_in.map(|secs| Utc::now() + Duration::seconds(secs as i64))
+    }
+    /// Scopes the server reports having granted. Names this crate doesn't recognize are skipped.
+    pub(crate) fn scopes(&self) -> Option<Vec<Scope>> {
+        self.scope.as_ref().map(|scope| {
+            scope.split_whitespace().filter_map(|name| name.parse().ok()).collect()
+        })
+    }
+    /// Interpret the status and body returned by the token endpoint.
+    pub(crate) fn parse(status: StatusCode, body: &str) -> Result<Self, AuthError> {
+        if status.is_success() {
+            let token: Self = serde_json::from_str(body).map_err(AuthError::Json)?;
+            // The token is always sent as a bearer token, whatever (casing of) type is reported.
+            if !token.token_type.eq_ignore_ascii_case("bearer") {
+                warn!("unexpected token type {:?}; using it as a bearer token anyway", token.token_type);
+            }
+            return Ok(token);
+        }
+        #[derive(Debug, Default, Deserialize)]
+        struct ErrorBody {
+            error: Option<String>,
+            error_description: Option<String>,
+        }
+        let err_body: ErrorBody = serde_json::from_str(body).unwrap_or_default();
+        Err(AuthError::Rejected {
+            status,
+            error: err_body.error,
+            description: err_body.error_description,
+        })
+    }
+}
+
+impl From<reqwest::Error> for AuthError {
+    fn from(err: reqwest::Error) -> Self {
+        AuthError::Http(err)
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthError::Http(err) => write!(f, "token request failed: {}", err),
+            AuthError::Json(err) => write!(f, "malformed token response: {}", err),
+            AuthError::InvalidToken(err) => write!(f, "unusable access token: {}", err),
+            AuthError::Rejected { status, error, description } => {
+                write!(f, "token request rejected ({})", status)?;
+                if let Some(error) = error {
+                    write!(f, ": {}", error)?;
+                }
+                if let Some(description) = description {
+                    write!(f, " ({})", description)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for AuthError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AuthError::Http(err) => Some(err),
+            AuthError::Json(err) => Some(err),
+            AuthError::InvalidToken(err) => Some(err),
+            AuthError::Rejected { .. } => None,
+        }
+    }
+}
+
+/// Build the URL a user must visit in order to grant the application access to their account.
+/// Once they accept, fimfiction redirects them to `redirect_uri` with a `code` query parameter,
+/// which can be passed to `Application::authorize_from_authorization_code`.
+/// Pass the same `scopes` to `ApplicationBuilder::scopes` so that they're known once authorized.
+pub fn authorization_url(client_id: &str, redirect_uri: &str, scopes: &[Scope]) -> Url {
+    let scope = scopes.iter().map(|scope| scope.as_str()).collect::<Vec<_>>().join(" ");
+    let mut url = Url::parse("https://www.fimfiction.net/authorize-app").unwrap();
+    url.query_pairs_mut()
+        .append_pair("client_id", client_id)
+        .append_pair("response_type", "code")
+        .append_pair("scope", &scope)
+        .append_pair("redirect_uri", redirect_uri);
+    url
+}
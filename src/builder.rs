@@ -0,0 +1,373 @@
+//! Configuration of an `Application` or `AsyncApplication`, prior to authorizing it.
+use chrono::{DateTime, Utc};
+use reqwest::header::{self, HeaderMap};
+#[cfg(feature = "blocking")]
+use reqwest::blocking;
+use reqwest::{StatusCode, Url};
+use std::time::Duration;
+
+#[cfg(feature = "blocking")]
+use crate::application::Application;
+#[cfg(feature = "async")]
+use crate::async_application::AsyncApplication;
+use crate::auth::{AuthError, Scope, TokenResponse};
+use crate::error::ApiError;
+use crate::query::Query;
+use crate::rate_limit::RateLimiter;
+
+/// Root of the fimfiction API, against which all endpoints are resolved by default.
+pub const DEFAULT_BASE_URL: &str = "https://www.fimfiction.net/api/v2/";
+/// User-Agent sent with every request, unless overridden.
+pub const DEFAULT_USER_AGENT: &str = concat!("rust-fimfiction-api/", env!("CARGO_PKG_VERSION"));
+
+/// Settings shared by the blocking and async applications.
+#[derive(Clone, Debug)]
+pub(crate) struct Settings {
+    pub base_url: Url,
+    pub user_agent: String,
+    /// Number of times to re-send a request which failed with 429 or 5xx.
+    pub max_retries: u32,
+    /// Delay before the first retry, absent a Retry-After header. Doubles with each retry.
+    pub retry_base_delay: Duration,
+    /// Longest the server may have us wait before a retry; if it asks for more, we give up instead.
+    pub max_retry_delay: Duration,
+    /// Shared by every application built from the same builder.
+    pub rate_limiter: Option<RateLimiter>,
+    /// Number of responses each authorized application may cache, if caching is enabled.
+    pub cache_capacity: Option<usize>,
+    /// Limit on the time taken by each request to the API, overriding the client's own.
+    pub timeout: Option<Duration>,
+    /// Scopes assumed to have been granted, when the token endpoint doesn't say.
+    pub scopes: Option<Vec<Scope>>,
+    /// Whether responses should carry a `debug` block.
+    pub request_debug: bool,
+}
+
+impl Settings {
+    /// Scopes granted along with `token`.
+    pub fn granted_scopes(&self, token: &TokenResponse) -> Option<Vec<Scope>> {
+        token.scopes().or_else(|| self.scopes.clone())
+    }
+    /// Build the full URL to the given endpoint.
+    /// Any part of `tail` which comes from a parameter should have gone through `EndpointPath`.
+    pub fn endpoint<T: AsRef<str>>(&self, tail: T) -> Url {
+        self.base_url.join(tail.as_ref()).unwrap()
+    }
+    /// Same as `endpoint`, for a `tail` which wasn't built by this crate: fails with
+    /// `ApiError::InvalidPath` unless it resolves to somewhere under `base_url`.
+    pub fn endpoint_within(&self, tail: &str) -> Result<Url, ApiError> {
+        match self.base_url.join(tail) {
+            Ok(url) if url.as_str().starts_with(self.base_url.as_str()) => Ok(url),
+            _ => Err(ApiError::InvalidPath(tail.to_owned())),
+        }
+    }
+    /// Build the full URL to the given endpoint, with `query` as its query string.
+    pub fn endpoint_with_query<T: AsRef<str>>(&self, tail: T, query: &Query) -> Url {
+        let mut url = self.endpoint(tail);
+        url.query_pairs_mut().extend_pairs(query.pairs());
+        url
+    }
+    /// Ask for `url` to be answered without a `debug` block, if so configured.
+    pub fn apply_request_debug(&self, url: &mut Url) {
+        if !self.request_debug && !url.query_pairs().any(|(key, _)| key == "debug") {
+            url.query_pairs_mut().append_pair("debug", "false");
+        }
+    }
+    /// How long to wait before sending the next request, to respect the rate limit.
+    /// `None` if it may be sent immediately.
+    pub fn throttle(&self) -> Option<Duration> {
+        let delay = self.rate_limiter.as_ref()?.reserve();
+        if delay > Duration::from_secs(0) {
+            Some(delay)
+        } else {
+            None
+        }
+    }
+    /// How long to wait before retrying a request which received the given response,
+    /// having already retried it `retries` times. `None` if it shouldn't be retried,
+    /// including when the server asks us to wait longer than `max_retry_delay`.
+    pub fn retry_delay(&self, status: StatusCode, headers: &HeaderMap, retries: u32) -> Option<Duration> {
+        if retries >= self.max_retries
+            || (status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error())
+        {
+            return None;
+        }
+        let backoff = || {
+            self.retry_base_delay.checked_mul(2u32.saturating_pow(retries))
+                .unwrap_or_else(|| Duration::from_secs(u64::from(u32::MAX)))
+        };
+        let delay = headers.get(header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        match delay {
+            Some(delay) if delay > self.max_retry_delay => {
+                warn!("server asked for a retry after {:?}, which is too long to wait", delay);
+                None
+            }
+            Some(delay) => Some(delay),
+            None => Some(backoff().min(self.max_retry_delay)),
+        }
+    }
+}
+
+/// Form of `url` which is safe to log: any credentials in it are replaced by "***".
+pub(crate) fn redact(url: &Url) -> Url {
+    const SECRET_PARAMS: [&str; 4] = ["access_token", "client_secret", "code", "refresh_token"];
+    let mut url = url.clone();
+    if url.password().is_some() {
+        let _ = url.set_password(Some("***"));
+    }
+    if url.query_pairs().any(|(key, _)| SECRET_PARAMS.contains(&key.as_ref())) {
+        let pairs: Vec<(String, String)> = url.query_pairs()
+            .map(|(key, value)| {
+                let value = if SECRET_PARAMS.contains(&key.as_ref()) { "***".into() } else { value };
+                (key.into_owned(), value.into_owned())
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url
+}
+
+/// Parse a Retry-After header, which is either a number of seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(secs) = value.trim().parse() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    // A date in the past means "retry now".
+    Some((date.with_timezone(&Utc) - Utc::now()).to_std().unwrap_or_default())
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            base_url: Url::parse(DEFAULT_BASE_URL).unwrap(),
+            user_agent: DEFAULT_USER_AGENT.to_owned(),
+            max_retries: 0,
+            retry_base_delay: Duration::from_secs(1),
+            max_retry_delay: Duration::from_secs(60),
+            rate_limiter: None,
+            cache_capacity: None,
+            timeout: None,
+            scopes: None,
+            request_debug: true,
+        }
+    }
+}
+
+/// Configures an application, and then authorizes it via one of the `authorize_*` methods.
+/// `Application::authorize_from_client_credentials` etc. are shorthand for using the default configuration.
+#[derive(Clone, Debug, Default)]
+pub struct ApplicationBuilder {
+    settings: Settings,
+    #[cfg(feature = "blocking")]
+    client: Option<blocking::Client>,
+    #[cfg(feature = "async")]
+    async_client: Option<reqwest::Client>,
+    /// Applied to the default clients, i.e. unless one was supplied.
+    proxies: Vec<reqwest::Proxy>,
+    /// Whether the default clients accept compressed responses; `None` leaves it to reqwest.
+    compression: Option<bool>,
+}
+
+impl ApplicationBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Send requests (including authorization) to `base_url` rather than `DEFAULT_BASE_URL`,
+    /// e.g. to point at a mock server during testing.
+    /// Endpoints are joined onto it, so it should end in a '/'.
+    pub fn base_url(mut self, base_url: Url) -> Self {
+        self.settings.base_url = base_url;
+        self
+    }
+    /// Identify the application to fimfiction with `user_agent` instead of `DEFAULT_USER_AGENT`.
+    /// Used for the token request as well as every request after it.
+    /// Fimfiction asks that bots identify themselves, so include the application's name
+    /// and a way to contact its operator, e.g. "my-indexer/1.2 (+https://example.com/contact)".
+    pub fn user_agent<S: Into<String>>(mut self, user_agent: S) -> Self {
+        self.settings.user_agent = user_agent.into();
+        self
+    }
+    /// Automatically re-send requests which fail with HTTP 429 (rate limited) or 5xx,
+    /// up to `max_retries` times. Defaults to 0, i.e. no retries.
+    /// Requests which aren't safe to repeat (e.g. creating a resource) are never retried.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.settings.max_retries = max_retries;
+        self
+    }
+    /// Delay before the first retry, when the server doesn't specify one via Retry-After.
+    /// Each subsequent retry waits twice as long as the previous one. Defaults to 1 second.
+    pub fn retry_base_delay(mut self, delay: Duration) -> Self {
+        self.settings.retry_base_delay = delay;
+        self
+    }
+    /// Longest to wait before any retry. If the server's Retry-After asks for longer, the request
+    /// isn't retried, and its error is returned instead. Exponential backoff stops growing here.
+    /// Defaults to 60 seconds.
+    pub fn max_retry_delay(mut self, delay: Duration) -> Self {
+        self.settings.max_retry_delay = delay;
+        self
+    }
+    /// Send at most `requests_per_second` requests per second (on average),
+    /// delaying requests as needed. Bursts of up to `requests_per_second` requests are allowed.
+    /// The limit is shared by every application authorized through this builder (or clones of it).
+    ///
+    /// Panics if `requests_per_second` isn't positive.
+    pub fn rate_limit(mut self, requests_per_second: f64) -> Self {
+        self.settings.rate_limiter = Some(RateLimiter::new(requests_per_second));
+        self
+    }
+    /// Remember up to `capacity` responses (the least recently used are forgotten first),
+    /// so that repeated GETs of the same URL can be answered with a conditional request.
+    /// If the server replies 304 Not Modified, the remembered response is used instead.
+    /// Only responses with an ETag or Last-Modified header are remembered.
+    /// Each application authorized through this builder gets a cache of its own (shared only with
+    /// its clones), since responses may differ from one user to another.
+    /// Disabled by default.
+    pub fn cache_capacity(mut self, capacity: usize) -> Self {
+        self.settings.cache_capacity = Some(capacity);
+        self
+    }
+    /// Give up on any request to the API which hasn't completed within `timeout`,
+    /// failing with `ApiError::Timeout`. Each retry gets a fresh `timeout`.
+    /// By default the client's own timeout applies: 30 seconds for the default blocking client,
+    /// and none for the default async client.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.settings.timeout = Some(timeout);
+        self
+    }
+    /// Treat `scopes` as the ones granted by the user, unless the token endpoint reports otherwise.
+    /// Typically the same scopes which were passed to `authorization_url`.
+    /// Methods requiring a scope outside this set then fail with `ApiError::MissingScope`
+    /// without contacting the server. By default the granted scopes are unknown, and nothing is checked.
+    pub fn scopes(mut self, scopes: &[Scope]) -> Self {
+        self.settings.scopes = Some(scopes.to_vec());
+        self
+    }
+    /// Pass `false` to ask fimfiction to leave the `debug` block out of its responses
+    /// (via a `debug=false` parameter), making them a little smaller.
+    /// `TypedApiResponse::debug` is then `None`. Enabled by default.
+    pub fn request_debug(mut self, enabled: bool) -> Self {
+        self.settings.request_debug = enabled;
+        self
+    }
+    /// Perform all requests through `client`, rather than a default-constructed one.
+    /// Useful for configuring timeouts, proxies, TLS, etc.
+    /// Only used by the blocking `authorize_*` methods.
+    #[cfg(feature = "blocking")]
+    pub fn client(mut self, client: blocking::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+    /// Counterpart to `client`, used by the `authorize_*_async` methods.
+    #[cfg(feature = "async")]
+    pub fn async_client(mut self, client: reqwest::Client) -> Self {
+        self.async_client = Some(client);
+        self
+    }
+    /// Send every request, including the token exchange, through `proxy`.
+    /// May be called more than once, e.g. to use different proxies for HTTP and HTTPS.
+    /// Only applies to the default clients: configure a client passed to `client` or
+    /// `async_client` with its own proxy instead.
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+    /// Whether to ask for responses to be compressed, and transparently decompress them.
+    /// Requires the `gzip` and/or `brotli` features of this crate, which enable the corresponding
+    /// reqwest features; with either enabled, compression is on unless disabled here.
+    /// Without them, this has no effect. Like `proxy`, only applies to the default clients.
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.compression = Some(enabled);
+        self
+    }
+
+    /// Authorize an application via client-specific credentials.
+    /// See https://www.fimfiction.net/developers/api/v2/docs/oauth#authorisation-code
+    #[cfg(feature = "blocking")]
+    pub fn authorize_client_credentials(mut self, client_id: &str, client_secret: &str)
+        -> Result<Application, AuthError>
+    {
+        let client = match self.client.take() {
+            Some(client) => client,
+            None => self.default_client()?,
+        };
+        Application::authorize_client_credentials_with(self.settings, client, client_id, client_secret)
+    }
+    /// Authorize an application on behalf of a user.
+    /// See `Application::authorize_from_authorization_code`.
+    #[cfg(feature = "blocking")]
+    pub fn authorize_authorization_code(mut self, client_id: &str, client_secret: &str, code: &str, redirect_uri: &str)
+        -> Result<(Application, Option<String>), AuthError>
+    {
+        let client = match self.client.take() {
+            Some(client) => client,
+            None => self.default_client()?,
+        };
+        Application::authorize_authorization_code_with(self.settings, client, client_id, client_secret, code, redirect_uri)
+    }
+    /// Async version of `authorize_client_credentials`.
+    #[cfg(feature = "async")]
+    pub async fn authorize_client_credentials_async(mut self, client_id: &str, client_secret: &str)
+        -> Result<AsyncApplication, AuthError>
+    {
+        let client = match self.async_client.take() {
+            Some(client) => client,
+            None => self.default_async_client()?,
+        };
+        AsyncApplication::authorize_client_credentials_with(self.settings, client, client_id, client_secret).await
+    }
+    /// Async version of `authorize_authorization_code`.
+    #[cfg(feature = "async")]
+    pub async fn authorize_authorization_code_async(mut self, client_id: &str, client_secret: &str, code: &str, redirect_uri: &str)
+        -> Result<(AsyncApplication, Option<String>), AuthError>
+    {
+        let client = match self.async_client.take() {
+            Some(client) => client,
+            None => self.default_async_client()?,
+        };
+        AsyncApplication::authorize_authorization_code_with(self.settings, client, client_id, client_secret, code, redirect_uri)
+            .await
+    }
+
+    /// Client to use when none was supplied via `client`.
+    #[cfg(feature = "blocking")]
+    fn default_client(&self) -> Result<blocking::Client, reqwest::Error> {
+        let mut builder = blocking::Client::builder();
+        for proxy in &self.proxies {
+            builder = builder.proxy(proxy.clone());
+        }
+        if let Some(_enabled) = self.compression {
+            #[cfg(feature = "gzip")]
+            {
+                builder = builder.gzip(_enabled);
+            }
+            #[cfg(feature = "brotli")]
+            {
+                builder = builder.brotli(_enabled);
+            }
+        }
+        builder.build()
+    }
+    /// Client to use when none was supplied via `async_client`.
+    #[cfg(feature = "async")]
+    fn default_async_client(&self) -> Result<reqwest::Client, reqwest::Error> {
+        let mut builder = reqwest::Client::builder();
+        for proxy in &self.proxies {
+            builder = builder.proxy(proxy.clone());
+        }
+        if let Some(_enabled) = self.compression {
+            #[cfg(feature = "gzip")]
+            {
+                builder = builder.gzip(_enabled);
+            }
+            #[cfg(feature = "brotli")]
+            {
+                builder = builder.brotli(_enabled);
+            }
+        }
+        builder.build()
+    }
+}
@@ -0,0 +1,98 @@
+//! Client-side caching of responses, revalidated with conditional requests.
+use reqwest::header::{self, HeaderMap, HeaderValue};
+use reqwest::{StatusCode, Url};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Least-recently-used cache of response bodies, keyed by the URL they were retrieved from.
+/// Only responses carrying an ETag or Last-Modified header are stored, since they're the only
+/// ones which can be revalidated; each hit still costs a (conditional) request, but a 304 has no body.
+/// Clones share the same entries.
+#[derive(Clone, Debug)]
+pub(crate) struct Cache {
+    inner: Arc<Mutex<Entries>>,
+}
+
+#[derive(Debug)]
+struct Entries {
+    capacity: usize,
+    by_url: HashMap<Url, Entry>,
+    /// Incremented on every access, to order entries by recency.
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    body: Vec<u8>,
+    last_used: u64,
+}
+
+impl Cache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Entries {
+                capacity,
+                by_url: HashMap::new(),
+                clock: 0,
+            })),
+        }
+    }
+    /// Make a GET of `url` conditional on the cached copy (if any) having changed.
+    pub fn add_validators(&self, url: &Url, headers: &mut HeaderMap) {
+        let entries = self.inner.lock().unwrap();
+        if let Some(entry) = entries.by_url.get(url) {
+            if let Some(ref etag) = entry.etag {
+                headers.insert(header::IF_NONE_MATCH, etag.clone());
+            }
+            if let Some(ref last_modified) = entry.last_modified {
+                headers.insert(header::IF_MODIFIED_SINCE, last_modified.clone());
+            }
+        }
+    }
+    /// Status and body to interpret a GET of `url` as, given the actual response.
+    /// A 304 is replaced by the cached copy; a fresh response is stored for next time.
+    pub fn resolve(&self, url: &Url, status: StatusCode, headers: &HeaderMap, body: Vec<u8>)
+        -> (StatusCode, Vec<u8>)
+    {
+        let mut entries = self.inner.lock().unwrap();
+        entries.clock += 1;
+        let now = entries.clock;
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some(entry) = entries.by_url.get_mut(url) {
+                debug!("cached copy of {} is still valid", url);
+                entry.last_used = now;
+                return (StatusCode::OK, entry.body.clone());
+            }
+        }
+        let etag = headers.get(header::ETAG).cloned();
+        let last_modified = headers.get(header::LAST_MODIFIED).cloned();
+        if status.is_success() && (etag.is_some() || last_modified.is_some()) && entries.capacity > 0 {
+            if entries.by_url.len() >= entries.capacity && !entries.by_url.contains_key(url) {
+                entries.evict_oldest();
+            }
+            entries.by_url.insert(url.clone(), Entry {
+                etag,
+                last_modified,
+                body: body.clone(),
+                last_used: now,
+            });
+        }
+        (status, body)
+    }
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().by_url.clear();
+    }
+}
+
+impl Entries {
+    fn evict_oldest(&mut self) {
+        let oldest = self.by_url.iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(url, _)| url.clone());
+        if let Some(url) = oldest {
+            self.by_url.remove(&url);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Errors which may occur when querying the API.
+use reqwest::StatusCode;
+#[cfg(any(feature = "blocking", feature = "async"))]
+use serde::de::DeserializeOwned;
+use serde::de::Deserializer;
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+
+use crate::auth::Scope;
+use crate::resources::Story;
+
+/// Failure of a request to the API.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request could not be sent, or the response could not be read.
+    Http(reqwest::Error),
+    /// The request didn't complete within the time allowed (see `ApplicationBuilder::timeout`).
+    Timeout(reqwest::Error),
+    /// Fimfiction responded with a non-2xx status (other than 401 or 403).
+    /// `errors` holds whatever error objects it returned alongside it (possibly none).
+    Status {
+        status: StatusCode,
+        errors: Vec<ApiErrorObject>,
+    },
+    /// Fimfiction rejected the access token (401 Unauthorized), e.g. because it expired or was revoked.
+    /// Authorizing the application again may help.
+    Unauthorized {
+        errors: Vec<ApiErrorObject>,
+    },
+    /// Fimfiction accepted the access token, but refused the request (403 Forbidden),
+    /// e.g. because the user didn't grant a scope it requires.
+    /// `missing_scope` is that scope, if the error body names one.
+    Forbidden {
+        missing_scope: Option<Scope>,
+        errors: Vec<ApiErrorObject>,
+    },
+    /// The response indicated success, but its body didn't have the expected shape.
+    Json(serde_json::Error),
+    /// The request wasn't sent, because the user didn't grant the application a scope it requires.
+    MissingScope(Scope),
+    /// The request wasn't sent, because it concerns the authorized user,
+    /// but the application was authorized via client credentials, i.e. without a user.
+    NoUser,
+    /// The request wasn't sent, because there's no endpoint for resources of the given type.
+    UnknownResourceType(String),
+    /// The request wasn't sent, because the given URL isn't that of a story on fimfiction.
+    InvalidStoryUrl(String),
+    /// The request wasn't sent, because the given path doesn't resolve to an endpoint of the API.
+    InvalidPath(String),
+}
+
+/// Single entry of the `errors` array in an error response.
+/// See https://jsonapi.org/format/#error-objects
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiErrorObject {
+    /// Short, human-readable summary of the problem
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Human-readable explanation specific to this occurrence of the problem
+    #[serde(default)]
+    pub detail: Option<String>,
+    /// HTTP status code, as a string
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub status: Option<String>,
+    /// Application-specific error code
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub code: Option<String>,
+}
+
+/// Interpret the status and body of a response as either the expected `T`, or an error.
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn parse_response<T: DeserializeOwned>(status: StatusCode, body: &[u8]) -> Result<T, ApiError> {
+    check_status(status, body)?;
+    serde_json::from_slice(body).map_err(ApiError::Json)
+}
+
+/// Interpret the body of a non-2xx response as an error, ignoring the body of a successful one.
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn check_status(status: StatusCode, body: &[u8]) -> Result<(), ApiError> {
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(status_error(status, body))
+    }
+}
+
+/// Interpret the status and body of a non-2xx response as an error.
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn status_error(status: StatusCode, body: &[u8]) -> ApiError {
+    #[derive(Debug, Default, Deserialize)]
+    struct ErrorBody {
+        #[serde(default)]
+        errors: Vec<ApiErrorObject>,
+    }
+    let errors = serde_json::from_slice::<ErrorBody>(body).unwrap_or_default().errors;
+    match status {
+        StatusCode::UNAUTHORIZED => ApiError::Unauthorized { errors },
+        StatusCode::FORBIDDEN => ApiError::Forbidden {
+            missing_scope: mentioned_scope(&errors),
+            errors,
+        },
+        _ => ApiError::Status { status, errors },
+    }
+}
+
+/// First scope named (e.g. "write_stories") in any of `errors`.
+#[cfg(any(feature = "blocking", feature = "async"))]
+fn mentioned_scope(errors: &[ApiErrorObject]) -> Option<Scope> {
+    errors.iter()
+        .flat_map(|err| err.detail.iter().chain(&err.title).chain(&err.code))
+        .flat_map(|text| text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_')))
+        .find_map(|word| word.parse().ok())
+}
+
+/// JSON:API says `status` and `code` are strings, but be lenient in case they're sent as numbers.
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(match <Option<Value> as serde::Deserialize>::deserialize(deserializer)? {
+        None | Some(Value::Null) => None,
+        Some(Value::String(s)) => Some(s),
+        Some(other) => Some(other.to_string()),
+    })
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            ApiError::Timeout(err)
+        } else {
+            ApiError::Http(err)
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiError::Http(err) => write!(f, "request failed: {}", err),
+            ApiError::Timeout(err) => write!(f, "request timed out: {}", err),
+            ApiError::Status { status, errors } => {
+                write!(f, "fimfiction responded with {}", status)?;
+                for err in errors {
+                    if let Some(msg) = err.detail.as_ref().or(err.title.as_ref()) {
+                        write!(f, "; {}", msg)?;
+                    }
+                }
+                Ok(())
+            }
+            ApiError::Unauthorized { .. } => write!(f, "the access token was rejected"),
+            ApiError::Forbidden { missing_scope: Some(scope), .. } => {
+                write!(f, "forbidden; the {} scope is required", scope)
+            }
+            ApiError::Forbidden { missing_scope: None, errors } => {
+                write!(f, "forbidden")?;
+                for err in errors {
+                    if let Some(msg) = err.detail.as_ref().or(err.title.as_ref()) {
+                        write!(f, "; {}", msg)?;
+                    }
+                }
+                Ok(())
+            }
+            ApiError::Json(err) => write!(f, "unexpected response body: {}", err),
+            ApiError::MissingScope(scope) => write!(f, "the {} scope was not granted", scope),
+            ApiError::NoUser => write!(f, "the application isn't authorized on behalf of a user"),
+            ApiError::UnknownResourceType(type_) => write!(f, "no endpoint for resources of type {:?}", type_),
+            ApiError::InvalidStoryUrl(url) => write!(f, "not the URL of a fimfiction story: {:?}", url),
+            ApiError::InvalidPath(path) => write!(f, "not the path of an API endpoint: {:?}", path),
+        }
+    }
+}
+
+impl Error for ApiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ApiError::Http(err) => Some(err),
+            ApiError::Timeout(err) => Some(err),
+            ApiError::Status { .. } => None,
+            ApiError::Unauthorized { .. } => None,
+            ApiError::Forbidden { .. } => None,
+            ApiError::Json(err) => Some(err),
+            ApiError::MissingScope(_) => None,
+            ApiError::NoUser => None,
+            ApiError::UnknownResourceType(_) => None,
+            ApiError::InvalidStoryUrl(_) => None,
+            ApiError::InvalidPath(_) => None,
+        }
+    }
+}
+
+/// Disagreement between a story's `num_chapters` and the chapters fimfiction actually lists for it,
+/// found by `FimfictionApi::verify_chapter_count` (or `AsyncApplication::verify_chapter_count`).
+/// Not a failure of the request, so it isn't an `ApiError`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChapterCountMismatch {
+    /// The story's `num_chapters`.
+    pub claimed: u32,
+    /// Number of chapters retrieved from /stories/:id/chapters.
+    pub actual: u32,
+}
+
+impl ChapterCountMismatch {
+    /// The mismatch between `story` and the `actual` number of chapters, if there is one.
+    pub(crate) fn between(story: &Story, actual: usize) -> Option<Self> {
+        let claimed = story.attributes.num_chapters;
+        match actual as u32 {
+            actual if actual == claimed => None,
+            actual => Some(ChapterCountMismatch { claimed, actual }),
+        }
+    }
+}
@@ -1,4 +1,15 @@
+//! Client for the fimfiction.net API (v2).
+//!
+//! The blocking `Application` is behind the `blocking` feature (enabled by default),
+//! and the non-blocking `AsyncApplication` behind the `async` feature.
+//! The resource and response types are available either way; `ApplicationBuilder` needs one of the two.
+//! The `mock` feature adds `MockApplication`, an offline implementation of `FimfictionApi`,
+//! and the `text` feature adds `html_to_text`, for reading the HTML fields as plain text.
 extern crate chrono;
+#[cfg(feature = "async")]
+extern crate futures;
+#[cfg(feature = "text")]
+extern crate html_escape;
 #[macro_use]
 extern crate log;
 extern crate reqwest;
@@ -6,9 +17,55 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
-extern crate url_serde;
+#[cfg(feature = "async")]
+extern crate tokio;
+extern crate url;
+
+/// `EndpointPath` made of the given segments, as a `String`.
+macro_rules! path {
+    ($($segment:expr),+) => {
+        $crate::path::EndpointPath::new()$(.segment($segment))+.into_string()
+    };
+}
+
+pub mod api;
+#[cfg(feature = "blocking")]
 pub mod application;
+#[cfg(feature = "async")]
+pub mod async_application;
+pub mod auth;
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub mod builder;
+#[cfg(any(feature = "blocking", feature = "async"))]
+mod cache;
+pub mod error;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod path;
+pub mod query;
+#[cfg(any(feature = "blocking", feature = "async"))]
+mod rate_limit;
 pub mod resources;
+pub mod response;
+#[cfg(feature = "text")]
+pub mod text;
+pub mod write;
 
+pub use api::*;
+#[cfg(feature = "blocking")]
 pub use application::*;
+#[cfg(feature = "async")]
+pub use async_application::*;
+pub use auth::*;
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub use builder::*;
+pub use error::*;
+#[cfg(feature = "mock")]
+pub use mock::*;
+pub use path::*;
+pub use query::*;
 pub use resources::*;
+pub use response::*;
+#[cfg(feature = "text")]
+pub use text::*;
+pub use write::*;
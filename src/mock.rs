@@ -0,0 +1,133 @@
+//! In-memory stand-in for `Application`, for testing code written against `FimfictionApi`.
+//! Requires the `mock` feature.
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::api::FimfictionApi;
+use crate::error::ApiError;
+use crate::resources::{
+    warnings, BlogPost, BlogPostId, Bookshelf, BookshelfId, Chapter, ChapterId, Group, GroupId,
+    Story, StoryId, StoryTag, StoryTagId, User, UserId,
+};
+use crate::response::{
+    BlogPostResponse, BookshelfResponse, ChapterResponse, ChaptersResponse, GroupResponse,
+    StoryResponse, StoryTagResponse, TypedApiResponse, UserResponse,
+};
+
+/// Serves resources which were inserted ahead of time, without contacting fimfiction.
+/// Requesting any other resource fails with a 404 `ApiError::Status`, like the real API.
+/// Responses never have any `included` resources, but do have their `warnings`.
+#[derive(Debug, Default)]
+pub struct MockApplication {
+    /// Resources are kept serialized, since each request hands out a fresh copy.
+    /// Keyed by collection path (e.g. "stories") and id.
+    resources: HashMap<(&'static str, u32), Value>,
+}
+
+impl MockApplication {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn insert_blog_post(&mut self, post: BlogPost) -> &mut Self {
+        self.insert("blog-posts", post.id.as_u32(), &post)
+    }
+    pub fn insert_bookshelf(&mut self, bookshelf: Bookshelf) -> &mut Self {
+        self.insert("bookshelves", bookshelf.id.as_u32(), &bookshelf)
+    }
+    pub fn insert_chapter(&mut self, chapter: Chapter) -> &mut Self {
+        self.insert("chapters", chapter.id.as_u32(), &chapter)
+    }
+    pub fn insert_group(&mut self, group: Group) -> &mut Self {
+        self.insert("groups", group.id.as_u32(), &group)
+    }
+    pub fn insert_story(&mut self, story: Story) -> &mut Self {
+        self.insert("stories", story.id.as_u32(), &story)
+    }
+    pub fn insert_story_tag(&mut self, tag: StoryTag) -> &mut Self {
+        self.insert("story-tags", tag.id.as_u32(), &tag)
+    }
+    pub fn insert_user(&mut self, user: User) -> &mut Self {
+        self.insert("users", user.id.as_u32(), &user)
+    }
+
+    fn insert<R: Serialize>(&mut self, collection: &'static str, id: u32, resource: &R) -> &mut Self {
+        let value = serde_json::to_value(resource).expect("resources always serialize");
+        self.resources.insert((collection, id), value);
+        self
+    }
+    fn get<T: DeserializeOwned>(&self, collection: &'static str, id: u32) -> Result<TypedApiResponse<T>, ApiError> {
+        let value = self.resources.get(&(collection, id)).ok_or_else(not_found)?;
+        let (data, warnings) = warnings::collect(|| <T as serde::Deserialize>::deserialize(value));
+        Ok(respond(format!("/api/v2/{}/{}", collection, id), data.map_err(ApiError::Json)?, warnings))
+    }
+}
+
+fn not_found() -> ApiError {
+    ApiError::Status {
+        status: StatusCode::NOT_FOUND,
+        errors: Vec::new(),
+    }
+}
+
+fn respond<T>(uri: String, data: T, warnings: Vec<String>) -> TypedApiResponse<T> {
+    TypedApiResponse {
+        data,
+        included: Vec::new(),
+        uri,
+        method: "GET".to_owned(),
+        debug: None,
+        links: Default::default(),
+        meta: Default::default(),
+        warnings,
+    }
+}
+
+impl FimfictionApi for MockApplication {
+    fn blog_post(&self, id: BlogPostId) -> Result<BlogPostResponse, ApiError> {
+        self.get("blog-posts", id.as_u32())
+    }
+    fn bookshelf(&self, id: BookshelfId) -> Result<BookshelfResponse, ApiError> {
+        self.get("bookshelves", id.as_u32())
+    }
+    fn chapter(&self, id: ChapterId) -> Result<ChapterResponse, ApiError> {
+        self.get("chapters", id.as_u32())
+    }
+    fn group(&self, id: GroupId) -> Result<GroupResponse, ApiError> {
+        self.get("groups", id.as_u32())
+    }
+    fn story(&self, id: StoryId) -> Result<StoryResponse, ApiError> {
+        self.get("stories", id.as_u32())
+    }
+    /// The inserted chapters belonging to the story, which must itself have been inserted.
+    fn story_chapters(&self, id: StoryId) -> Result<ChaptersResponse, ApiError> {
+        if !self.resources.contains_key(&("stories", id.as_u32())) {
+            return Err(not_found());
+        }
+        let (chapters, warnings) = warnings::collect(|| {
+            let mut chapters = Vec::new();
+            for (&(collection, _), value) in &self.resources {
+                if collection != "chapters" {
+                    continue;
+                }
+                let chapter: Chapter = serde::Deserialize::deserialize(value)?;
+                let story = chapter.relationships.as_ref().map(|rel| rel.story.data.id);
+                if story == Some(id.as_u32()) {
+                    chapters.push(chapter);
+                }
+            }
+            Ok(chapters)
+        });
+        let mut chapters = chapters.map_err(ApiError::Json)?;
+        chapters.sort_by_key(|chapter| chapter.attributes.chapter_number);
+        Ok(respond(format!("/api/v2/stories/{}/chapters", id.as_u32()), chapters, warnings))
+    }
+    fn story_tag(&self, id: StoryTagId) -> Result<StoryTagResponse, ApiError> {
+        self.get("story-tags", id.as_u32())
+    }
+    fn user(&self, id: UserId) -> Result<UserResponse, ApiError> {
+        self.get("users", id.as_u32())
+    }
+}
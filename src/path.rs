@@ -0,0 +1,53 @@
+//! Paths of endpoints, relative to the API root (e.g. "stories/1234/chapters").
+use std::fmt::{self, Display};
+
+/// Path of an endpoint, built one segment at a time.
+/// Each segment is percent-encoded, so that no value can add segments of its own
+/// (or a query string, or a fragment) to the path.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EndpointPath {
+    encoded: String,
+}
+
+impl EndpointPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Append a segment, e.g. a collection name or an id.
+    pub fn segment<S: Display>(mut self, segment: S) -> Self {
+        if !self.encoded.is_empty() {
+            self.encoded.push('/');
+        }
+        let segment = segment.to_string();
+        // "." and ".." would otherwise be resolved against the path before them.
+        let dots_only = segment.bytes().all(|b| b == b'.');
+        for byte in segment.bytes() {
+            match byte {
+                b'.' if dots_only => self.encoded.push_str("%2E"),
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    self.encoded.push(byte as char)
+                }
+                _ => self.encoded.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        self
+    }
+    pub fn as_str(&self) -> &str {
+        &self.encoded
+    }
+    pub fn into_string(self) -> String {
+        self.encoded
+    }
+}
+
+impl Display for EndpointPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.encoded)
+    }
+}
+
+impl AsRef<str> for EndpointPath {
+    fn as_ref(&self) -> &str {
+        &self.encoded
+    }
+}
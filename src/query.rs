@@ -0,0 +1,431 @@
+//! Query string parameters which may accompany a request.
+//! See https://www.fimfiction.net/developers/api/v2/docs/requests
+use reqwest::Url;
+use std::collections::HashMap;
+
+use crate::resources::{CompletionStatus, ContentRating, StoryTagId, UserId};
+
+/// Which page of a collection to request.
+/// Serialized as `page[number]` and `page[size]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Page {
+    /// 1-based index of the page
+    pub number: u32,
+    /// Number of resources per page
+    pub size: u32,
+}
+
+/// Position within a collection to request the next page from, taken from a response's
+/// `links.next`; see `TypedApiResponse::next_cursor`.
+/// Holds whichever `page[...]` parameters the link carries, so it works the same whether
+/// fimfiction paginates the collection by page number or by an opaque cursor (`page[cursor]`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    params: Vec<(String, String)>,
+}
+
+impl Cursor {
+    /// The cursor for the page `url` links to, or `None` if it has no pagination parameters.
+    pub(crate) fn from_link(url: &Url) -> Option<Self> {
+        let params: Vec<(String, String)> = url.query_pairs()
+            .filter(|(key, _)| key.starts_with("page["))
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect();
+        if params.is_empty() {
+            None
+        } else {
+            Some(Cursor { params })
+        }
+    }
+    /// Whether fimfiction paginates this collection by cursor, rather than by page number.
+    pub fn is_opaque(&self) -> bool {
+        self.params.iter().any(|(key, _)| key == "page[cursor]")
+    }
+}
+
+/// Direction in which to sort a collection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Ascending,
+    Descending,
+}
+
+/// Attribute by which some kind of resource can be sorted.
+pub trait SortField {
+    /// Name of the attribute, as understood by the `sort` parameter.
+    fn name(&self) -> &'static str;
+}
+
+/// Sortable attributes of a story.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorySortField {
+    DatePublished,
+    DateUpdated,
+    DateModified,
+    Title,
+    NumViews,
+    TotalNumViews,
+    NumWords,
+    NumComments,
+    NumLikes,
+    Rating,
+}
+
+impl SortField for StorySortField {
+    fn name(&self) -> &'static str {
+        match self {
+            StorySortField::DatePublished => "date_published",
+            StorySortField::DateUpdated => "date_updated",
+            StorySortField::DateModified => "date_modified",
+            StorySortField::Title => "title",
+            StorySortField::NumViews => "num_views",
+            StorySortField::TotalNumViews => "total_num_views",
+            StorySortField::NumWords => "num_words",
+            StorySortField::NumComments => "num_comments",
+            StorySortField::NumLikes => "num_likes",
+            StorySortField::Rating => "rating",
+        }
+    }
+}
+
+/// Sortable attributes of a blog post.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlogPostSortField {
+    DatePosted,
+    Title,
+    NumViews,
+    NumComments,
+}
+
+impl SortField for BlogPostSortField {
+    fn name(&self) -> &'static str {
+        match self {
+            BlogPostSortField::DatePosted => "date_posted",
+            BlogPostSortField::Title => "title",
+            BlogPostSortField::NumViews => "num_views",
+            BlogPostSortField::NumComments => "num_comments",
+        }
+    }
+}
+
+/// Relationship of some kind of resource which can be requested via `include`.
+pub trait Include {
+    /// Path of the relationship, as understood by the `include` parameter.
+    fn path(&self) -> &'static str;
+}
+
+/// Relationships of a blog post which can be included in the response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlogPostInclude {
+    Author,
+    TaggedStory,
+}
+
+impl Include for BlogPostInclude {
+    fn path(&self) -> &'static str {
+        match self {
+            BlogPostInclude::Author => "author",
+            BlogPostInclude::TaggedStory => "tagged_story",
+        }
+    }
+}
+
+/// Relationships of a bookshelf which can be included in the response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BookshelfInclude {
+    User,
+}
+
+impl Include for BookshelfInclude {
+    fn path(&self) -> &'static str {
+        match self {
+            BookshelfInclude::User => "user",
+        }
+    }
+}
+
+/// Relationships of a chapter which can be included in the response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChapterInclude {
+    Story,
+}
+
+impl Include for ChapterInclude {
+    fn path(&self) -> &'static str {
+        match self {
+            ChapterInclude::Story => "story",
+        }
+    }
+}
+
+/// Relationships of a group which can be included in the response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupInclude {
+    Founder,
+}
+
+impl Include for GroupInclude {
+    fn path(&self) -> &'static str {
+        match self {
+            GroupInclude::Founder => "founder",
+        }
+    }
+}
+
+/// Relationships of a private message which can be included in the response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrivateMessageInclude {
+    Sender,
+    Receiver,
+}
+
+impl Include for PrivateMessageInclude {
+    fn path(&self) -> &'static str {
+        match self {
+            PrivateMessageInclude::Sender => "sender",
+            PrivateMessageInclude::Receiver => "receiver",
+        }
+    }
+}
+
+/// Relationships of a story which can be included in the response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StoryInclude {
+    Author,
+    Tags,
+}
+
+impl Include for StoryInclude {
+    fn path(&self) -> &'static str {
+        match self {
+            StoryInclude::Author => "author",
+            StoryInclude::Tags => "tags",
+        }
+    }
+}
+
+/// One sort key of the `sort` parameter.
+/// Descending keys are serialized with a '-' prefix, e.g. `-date_published`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sort<F> {
+    pub field: F,
+    pub direction: Direction,
+}
+
+impl<F: SortField> Sort<F> {
+    pub fn ascending(field: F) -> Self {
+        Sort { field, direction: Direction::Ascending }
+    }
+    pub fn descending(field: F) -> Self {
+        Sort { field, direction: Direction::Descending }
+    }
+    fn to_param(&self) -> String {
+        match self.direction {
+            Direction::Ascending => self.field.name().to_owned(),
+            Direction::Descending => format!("-{}", self.field.name()),
+        }
+    }
+}
+
+/// Accumulates the query string parameters for one request.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    params: Vec<(String, String)>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Request a specific page of a collection.
+    pub fn page(self, page: Page) -> Self {
+        self.param("page[number]", page.number.to_string())
+            .param("page[size]", page.size.to_string())
+    }
+    /// Same as `page`, but does nothing if `page` is `None`.
+    pub fn maybe_page(self, page: Option<Page>) -> Self {
+        match page {
+            Some(page) => self.page(page),
+            None => self,
+        }
+    }
+    /// Request the page `cursor` points to. Does nothing if `cursor` is `None`,
+    /// i.e. the first page is requested.
+    pub fn cursor(mut self, cursor: Option<&Cursor>) -> Self {
+        for (key, value) in cursor.map_or(&[][..], |cursor| &cursor.params[..]) {
+            self = self.param(key.as_str(), value.as_str());
+        }
+        self
+    }
+    /// Ask for the given relationships (e.g. "author", "tags") to be returned
+    /// in the `included` section of the response. Does nothing if `paths` is empty.
+    pub fn include(self, paths: &[&str]) -> Self {
+        if paths.is_empty() {
+            self
+        } else {
+            self.param("include", paths.join(","))
+        }
+    }
+    /// Order the collection by each of `sorts`, in order of precedence.
+    /// Does nothing if `sorts` is empty.
+    pub fn sort<F: SortField>(self, sorts: &[Sort<F>]) -> Self {
+        if sorts.is_empty() {
+            self
+        } else {
+            let keys: Vec<String> = sorts.iter().map(Sort::to_param).collect();
+            self.param("sort", keys.join(","))
+        }
+    }
+    /// Restrict which attributes are returned for each resource type (sparse fieldsets),
+    /// e.g. `{"story": ["title", "num_words"]}` becomes `fields[story]=title,num_words`.
+    pub fn fields(mut self, fields: &HashMap<&str, Vec<&str>>) -> Self {
+        // Sorted, so the resulting URL doesn't depend on hash order.
+        let mut types: Vec<&&str> = fields.keys().collect();
+        types.sort();
+        for type_ in types {
+            self = self.param(format!("fields[{}]", type_), fields[*type_].join(","));
+        }
+        self
+    }
+    /// Only return the resources matching every criterion of `filter`.
+    pub fn filter(mut self, filter: &Filter) -> Self {
+        for (key, value) in &filter.criteria {
+            self = self.param(format!("filter[{}]", key), value.as_str());
+        }
+        self
+    }
+    /// Add an arbitrary parameter, for anything not otherwise covered by this builder.
+    pub fn param<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.params.push((key.into(), value.into()));
+        self
+    }
+    /// The (key, value) pairs to encode into the query string.
+    pub fn pairs(&self) -> &[(String, String)] {
+        &self.params
+    }
+}
+
+/// Criteria restricting which resources of a collection are returned,
+/// each sent as a `filter[key]=value` parameter. Which keys are understood depends on the endpoint.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Filter {
+    criteria: Vec<(String, String)>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Add an arbitrary criterion, for anything not otherwise covered by this builder.
+    pub fn param<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.criteria.push((key.into(), value.into()));
+        self
+    }
+    /// Only match resources authored by the given user.
+    pub fn author(self, user: UserId) -> Self {
+        self.param("author", user.to_string())
+    }
+    pub fn content_rating(self, rating: ContentRating) -> Self {
+        self.param("content_rating", rating.as_str())
+    }
+    pub fn completion_status(self, status: CompletionStatus) -> Self {
+        self.param("completion_status", status.as_str())
+    }
+    pub fn is_empty(&self) -> bool {
+        self.criteria.is_empty()
+    }
+}
+
+/// Criteria for `Application::search_stories`.
+/// Every criterion is optional; those left unset don't restrict the results.
+#[derive(Clone, Debug, Default)]
+pub struct SearchQuery {
+    term: Option<String>,
+    tags: Vec<StoryTagId>,
+    exclude_tags: Vec<StoryTagId>,
+    content_rating: Option<ContentRating>,
+    completion_status: Option<CompletionStatus>,
+    sort: Vec<Sort<StorySortField>>,
+    page: Option<Page>,
+    filter: Filter,
+}
+
+impl SearchQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Free-form search text, as typed into the site's search box.
+    pub fn term<S: Into<String>>(mut self, term: S) -> Self {
+        self.term = Some(term.into());
+        self
+    }
+    /// Only match stories having all of these tags. Sent as `filter[tags]`, alongside any excluded tags.
+    /// Fimfiction documents no filter for stories having just one of several tags.
+    pub fn tags(mut self, tags: &[StoryTagId]) -> Self {
+        self.tags = tags.to_vec();
+        self
+    }
+    /// Only match stories having none of these tags.
+    pub fn exclude_tags(mut self, tags: &[StoryTagId]) -> Self {
+        self.exclude_tags = tags.to_vec();
+        self
+    }
+    pub fn content_rating(mut self, rating: ContentRating) -> Self {
+        self.content_rating = Some(rating);
+        self
+    }
+    pub fn completion_status(mut self, status: CompletionStatus) -> Self {
+        self.completion_status = Some(status);
+        self
+    }
+    /// Order results by `sort`. May be called repeatedly to break ties by subsequent keys.
+    pub fn sort(mut self, sort: Sort<StorySortField>) -> Self {
+        self.sort.push(sort);
+        self
+    }
+    pub fn page(mut self, page: Page) -> Self {
+        self.page = Some(page);
+        self
+    }
+    /// Further criteria, for anything not otherwise covered by this builder.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    pub(crate) fn to_query(&self) -> Query {
+        let mut query = Query::new().maybe_page(self.page).sort(&self.sort);
+        if let Some(ref term) = self.term {
+            query = query.param("query", term.as_str());
+        }
+        let mut filter = self.filter.clone();
+        // Excluded tags are given in the same list as required ones, negated.
+        let excluded = self.exclude_tags.iter().map(|id| format!("-{}", id));
+        let tags: Vec<String> = self.tags.iter().map(ToString::to_string).chain(excluded).collect();
+        if !tags.is_empty() {
+            filter = filter.param("tags", tags.join(","));
+        }
+        if let Some(ref rating) = self.content_rating {
+            filter = filter.content_rating(rating.clone());
+        }
+        if let Some(ref status) = self.completion_status {
+            filter = filter.completion_status(status.clone());
+        }
+        query.filter(&filter)
+    }
+    /// Same as `to_query`, but for the first page, whatever `page` says: only its size is kept.
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    pub(crate) fn to_first_page_query(&self) -> Query {
+        let query = SearchQuery { page: None, ..self.clone() }.to_query();
+        match self.page {
+            Some(page) => query.param("page[size]", page.size.to_string()),
+            None => query,
+        }
+    }
+}
+
+/// Comma-separated list of ids, as used by list-valued parameters.
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn join_ids<T: ToString>(ids: &[T]) -> String {
+    ids.iter().map(ToString::to_string).collect::<Vec<_>>().join(",")
+}
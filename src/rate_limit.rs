@@ -0,0 +1,50 @@
+//! Client-side throttling of requests.
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Token bucket limiting how often requests are sent.
+/// Clones share the same bucket, so that every user of it collectively respects the limit.
+#[derive(Clone, Debug)]
+pub(crate) struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Tokens added per second
+    rate: f64,
+    /// Maximum number of tokens which may accumulate, i.e. the largest allowed burst.
+    capacity: f64,
+    /// May be negative, when requests are queued up waiting for tokens.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: f64) -> Self {
+        assert!(requests_per_second > 0.0, "rate limit must be positive");
+        let capacity = requests_per_second.max(1.0);
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                rate: requests_per_second,
+                capacity,
+                tokens: capacity,
+                last_refill: Instant::now(),
+            })),
+        }
+    }
+    /// Take a token for one request, returning how long the caller must wait before sending it.
+    pub fn reserve(&self) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * bucket.rate).min(bucket.capacity);
+        bucket.last_refill = now;
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / bucket.rate)
+        }
+    }
+}
@@ -2,99 +2,512 @@
 use chrono::{DateTime, Utc};
 use reqwest::Url;
 use serde_json::Value;
-use std::collections::HashMap;
-use std::fmt::Debug;
-use url_serde;
-use url_serde::SerdeUrl;
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde::ser::Serializer;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Debug};
 
 /// Fimfiction often returns data inside a "data" key.
 /// This object provides a way to replicate that wrapping on the rust side.
+///
+/// An empty to-one relationship is sent as `"data": null` (and sometimes without `data` at all),
+/// so such relationships are modelled as `Data<Option<ResourceId>>`; both forms become `None`.
+/// Empty to-many relationships are sent as an empty array instead.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Data<T: Debug> {
     pub data: T,
+    /// Present on some relationships, e.g. to fetch the full related collection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<RelationshipLinks>,
+}
+
+/// `links` of a relationship. See https://jsonapi.org/format/#document-resource-object-relationships
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RelationshipLinks {
+    /// The relationship itself, e.g. for adding to or removing from it.
+    #[serde(rename = "self", default, skip_serializing_if = "Option::is_none")]
+    pub self_: Option<Url>,
+    /// The related resource(s), e.g. every tag of a story.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub related: Option<Url>,
 }
 
 /// When a query returns 'resources', these resources are transmitted only as
 /// references. i.e. the type and ID of the resource is returned, allowing one
 /// to make further queries accordingly or extract them from the 'included' object.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct ResourceId {
     #[serde(rename="type")]
     pub type_: String,
-    // TODO It's really an int though.
-    pub id: String,
+    // The type isn't known statically, so neither is the kind of id.
+    #[serde(with="string_id")]
+    pub id: u32,
+}
+
+impl ResourceId {
+    /// Endpoint (relative to the API root) from which this resource can be retrieved,
+    /// e.g. "stories/1234". `None` if its type can't be retrieved by id.
+    pub fn path(&self) -> Option<String> {
+        collection_path(&self.type_).map(|collection| path!(collection, self.id))
+    }
+}
+
+/// Endpoint of the collection holding resources of the given type, e.g. "story" -> "stories".
+/// `None` for types which can't be retrieved by id.
+pub fn collection_path(type_: &str) -> Option<&'static str> {
+    Some(match type_ {
+        "blog_post" => "blog-posts",
+        "bookshelf" => "bookshelves",
+        "chapter" => "chapters",
+        "group" => "groups",
+        "private_message" => "private-messages",
+        "story" => "stories",
+        "story_tag" => "story-tags",
+        "user" => "users",
+        _ => return None,
+    })
+}
+
+/// Fimfiction transmits ids as strings, even though they're always integers.
+/// (De)serialize them as such, while also accepting a bare integer.
+mod string_id {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StrOrInt {
+        Str(String),
+        Int(u32),
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+        match <StrOrInt as serde::Deserialize>::deserialize(deserializer)? {
+            StrOrInt::Str(s) => s.parse().map_err(de::Error::custom),
+            StrOrInt::Int(i) => Ok(i),
+        }
+    }
+    pub fn serialize<S: Serializer>(id: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(id)
+    }
 }
 
+/// Fimfiction marks missing dates inconsistently: with null, an empty string, or a zero date
+/// like "0000-00-00 00:00:00". Map all of those to `None`.
+mod lenient_datetime {
+    use super::*;
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+        let date = match <Option<String> as serde::Deserialize>::deserialize(deserializer)? {
+            Some(date) => date,
+            None => return Ok(None),
+        };
+        if date.is_empty() || date.starts_with("0000-00-00") {
+            return Ok(None);
+        }
+        date.parse().map(Some).map_err(de::Error::custom)
+    }
+    pub fn serialize<S: Serializer>(date: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => serializer.serialize_some(date),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+/// Problems noticed while deserializing which didn't stop it from succeeding, e.g. an undocumented
+/// attribute whose value wasn't of the expected shape. Serde has no way to pass context down to
+/// nested deserializers, so they're recorded per thread, for the response being parsed on it
+/// (see `collect`). Outside of one, they're only logged.
+pub(crate) mod warnings {
+    use std::cell::RefCell;
+
+    thread_local! {
+        /// Warnings of the innermost `collect` running on this thread, if any.
+        static WARNINGS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+    }
+
+    pub fn push(warning: String) {
+        warn!("{}", warning);
+        WARNINGS.with(|warnings| {
+            if let Some(ref mut warnings) = *warnings.borrow_mut() {
+                warnings.push(warning);
+            }
+        });
+    }
+    /// Run `f`, returning the warnings recorded while it ran.
+    /// Any `collect` it's nested in sees none of them, and gets its own back afterwards,
+    /// even if `f` panics.
+    pub fn collect<R, F: FnOnce() -> R>(f: F) -> (R, Vec<String>) {
+        struct Restore(Option<Vec<String>>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let outer = self.0.take();
+                WARNINGS.with(|warnings| *warnings.borrow_mut() = outer);
+            }
+        }
+        let _restore = Restore(WARNINGS.with(|warnings| warnings.replace(Some(Vec::new()))));
+        let result = f();
+        let collected = WARNINGS.with(|warnings| warnings.borrow_mut().take());
+        (result, collected.unwrap_or_default())
+    }
+}
+
+/// Deserialize an attribute which fimfiction doesn't document, and so might change shape.
+/// If it isn't of the expected form, record a warning and use its default value instead.
+fn undocumented<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + Default,
+{
+    let value = <Value as serde::Deserialize>::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).unwrap_or_else(|err| {
+        warnings::push(format!("undocumented {} attribute has an unexpected value: {}",
+            std::any::type_name::<T>(), err));
+        T::default()
+    }))
+}
+
+/// Declare an id newtype for one kind of resource, so that e.g. a chapter id
+/// can't be passed where a story id is expected.
+macro_rules! resource_id {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub struct $name(pub u32);
+
+        impl $name {
+            pub fn as_u32(&self) -> u32 {
+                self.0
+            }
+        }
+        impl From<u32> for $name {
+            fn from(id: u32) -> Self {
+                $name(id)
+            }
+        }
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+        impl serde::Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                string_id::serialize(&self.0, serializer)
+            }
+        }
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                string_id::deserialize(deserializer).map($name)
+            }
+        }
+    };
+}
+
+resource_id!(
+    /// Id of a `BlogPost`
+    BlogPostId
+);
+resource_id!(
+    /// Id of a `Bookshelf`
+    BookshelfId
+);
+resource_id!(
+    /// Id of a `Chapter`
+    ChapterId
+);
+resource_id!(
+    /// Id of a `Comment`
+    CommentId
+);
+resource_id!(
+    /// Id of a `Follow`
+    FollowId
+);
+resource_id!(
+    /// Id of a `Group`
+    GroupId
+);
+resource_id!(
+    /// Id of a `GroupThread`
+    GroupThreadId
+);
+resource_id!(
+    /// Id of a `PrivateMessage`
+    PrivateMessageId
+);
+resource_id!(
+    /// Id of a `Story`
+    StoryId
+);
+impl StoryId {
+    /// Extract the id from the URL of a story on fimfiction, e.g.
+    /// "https://www.fimfiction.net/story/12345/some-title" or the short "https://fimfiction.net/s/12345".
+    /// The scheme may be omitted. Returns `None` if it isn't such a URL.
+    pub fn from_url(url: &str) -> Option<Self> {
+        let url = Url::parse(url)
+            .or_else(|_| Url::parse(&format!("https://{}", url)))
+            .ok()?;
+        match url.host_str()? {
+            "fimfiction.net" | "www.fimfiction.net" => {}
+            _ => return None,
+        }
+        let mut segments = url.path_segments()?;
+        match segments.next()? {
+            "story" | "s" => {}
+            _ => return None,
+        }
+        segments.next()?.parse().ok().map(StoryId)
+    }
+}
+resource_id!(
+    /// Id of a `StoryTag`
+    StoryTagId
+);
+resource_id!(
+    /// Id of a `User`
+    UserId
+);
+
 #[derive(Debug, Deserialize, Serialize)]
-pub struct TypedResource<Attr: Debug, Rel: Debug> {
+pub struct TypedResource<Id: Debug, Attr: Debug, Rel: Debug> {
     // Because this is strongly typed, we already know the value of the 'type' field.
     // Let serde manage it whenever we deserialize into an enum (where type is one of many).
     //#[serde(rename="type")]
     //type_: String,
-    // TODO It's really an int though.
-    pub id: String,
+    pub id: Id,
     pub attributes: Attr,
     /// When accessed through the "included" field, no relationships are shown.
     pub relationships: Option<Rel>,
     // TODO: learn more about these types and make them type-safe
     #[serde(default)]
-    pub links: HashMap<String, SerdeUrl>,
+    pub links: HashMap<String, Url>,
     #[serde(default)]
     pub meta: HashMap<String, Value>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-/// List of differently sized avatar images available
+/// Resource retrieved with only some of its attributes, via sparse fieldsets.
+/// `Attr` is a caller-defined struct holding just the requested attributes,
+/// and relationships are left unparsed.
+pub type Sparse<Id, Attr> = TypedResource<Id, Attr, Value>;
+
+/// List of differently sized avatar images available, keyed by their size in pixels.
+/// Which sizes are present varies from avatar to avatar
+/// (e.g. 192 was not found for https://www.fimfiction.net/api/v2/groups/209275,
+/// and 16 was not found for id 33084), so none are assumed.
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
 pub struct Avatar {
-    // TODO: how many of these sizes are optional?
-    // 192 was not found for https://www.fimfiction.net/api/v2/groups/209275
-    // 16 was not found for id 33084
-    // TODO: Maybe some sparse vector type is better.
-    #[serde(default, with = "url_serde", rename="16")]
-    pub size16: Option<Url>,
-    #[serde(with = "url_serde", rename="32")]
-    pub size32: Url,
-    #[serde(with = "url_serde", rename="48")]
-    pub size48: Url,
-    #[serde(with = "url_serde", rename="64")]
-    pub size64: Url,
-    #[serde(with = "url_serde", rename="96")]
-    pub size96: Url,
-    #[serde(with = "url_serde", rename="128")]
-    pub size128: Url,
-    #[serde(default, with = "url_serde", rename="192")]
-    pub size192: Option<Url>,
-    #[serde(with = "url_serde", rename="256")]
-    pub size256: Url,
-    #[serde(with = "url_serde", rename="384")]
-    pub size384: Url,
-    #[serde(with = "url_serde", rename="512")]
-    pub size512: Url,
+    pub sizes: BTreeMap<u32, Url>,
+}
+
+impl Avatar {
+    /// Image of exactly `px` pixels, if there is one.
+    pub fn get(&self, px: u32) -> Option<&Url> {
+        self.sizes.get(&px)
+    }
+    /// Image whose size is nearest to `px`, preferring the larger one in case of a tie.
+    pub fn closest(&self, px: u32) -> Option<&Url> {
+        self.closest_size(px).and_then(|size| self.get(size))
+    }
+    /// Size of the image `closest` would return.
+    pub fn closest_size(&self, px: u32) -> Option<u32> {
+        self.sizes.keys()
+            .copied()
+            .min_by_key(|&size| (size.abs_diff(px), Reverse(size)))
+    }
+    pub fn largest(&self) -> Option<&Url> {
+        self.sizes.values().next_back()
+    }
+    pub fn smallest(&self) -> Option<&Url> {
+        self.sizes.values().next()
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Avatar {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Keys are sent as strings. Parse them by hand, since not every deserializer
+        // (e.g. the one buffering an internally tagged Resource) will convert them to integers.
+        // Any which aren't sizes are skipped with a warning, rather than failing the whole user.
+        let by_name = <BTreeMap<String, Url> as serde::Deserialize>::deserialize(deserializer)?;
+        let sizes = by_name.into_iter()
+            .filter_map(|(size, url)| match size.parse() {
+                Ok(px) => Some((px, url)),
+                Err(err) => {
+                    warnings::push(format!("avatar has an image with an unexpected size {:?}: {}", size, err));
+                    None
+                }
+            })
+            .collect();
+        Ok(Avatar { sizes })
+    }
 }
 
 /// 'color' struct, as serialized by Fimfiction's API,
 /// e.g. the 'color' field within a story.
+/// Fimfiction sends `hex` without a '#'; when well-formed, it's normalized to lowercase '#rrggbb'.
 #[derive(Debug, Deserialize, Serialize)]
+#[serde(from = "ColorRepr")]
 pub struct Color {
     pub hex: String,
     pub rgb: [u8; 3],
 }
+#[derive(Deserialize)]
+struct ColorRepr {
+    hex: String,
+    rgb: [u8; 3],
+}
+impl From<ColorRepr> for Color {
+    fn from(repr: ColorRepr) -> Self {
+        let hex = match HexColor::parse(&repr.hex) {
+            Some(parsed) => parsed.to_string(),
+            None => repr.hex,
+        };
+        Color { hex, rgb: repr.rgb }
+    }
+}
+impl Color {
+    pub fn from_rgb(rgb: [u8; 3]) -> Self {
+        Color { hex: HexColor(rgb).to_string(), rgb }
+    }
+    /// Parse a '#rrggbb' string (the '#' is optional).
+    /// Returns `None` if it isn't of that form.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        HexColor::parse(hex).map(|parsed| Color::from_rgb(parsed.rgb()))
+    }
+    /// Whether `hex` is a well-formed '#rrggbb' string denoting the same color as `rgb`.
+    /// Only then can either field be trusted to describe the other.
+    pub fn validate(&self) -> bool {
+        HexColor::parse(&self.hex).map(|parsed| parsed.rgb()) == Some(self.rgb)
+    }
+}
+/// Color serialized as a '#rrggbb' string, e.g. the 'color' field of a bookshelf.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HexColor([u8; 3]);
+
+impl HexColor {
+    pub fn from_rgb(rgb: [u8; 3]) -> Self {
+        HexColor(rgb)
+    }
+    /// Parse a '#rrggbb' string (the '#' is optional).
+    /// Returns `None` if it isn't of that form.
+    pub fn parse(hex: &str) -> Option<Self> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if digits.len() != 6 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&digits[2*i..2*i + 2], 16).unwrap();
+        Some(HexColor([channel(0), channel(1), channel(2)]))
+    }
+    pub fn rgb(&self) -> [u8; 3] {
+        self.0
+    }
+}
+impl fmt::Display for HexColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0[0], self.0[1], self.0[2])
+    }
+}
+impl serde::Serialize for HexColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+impl<'de> serde::Deserialize<'de> for HexColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = <String as serde::Deserialize>::deserialize(deserializer)?;
+        HexColor::parse(&hex).ok_or_else(|| de::Error::custom(format!("invalid hex color: {:?}", hex)))
+    }
+}
+/// Deserialize an optional `HexColor`, where fimfiction uses an empty string for "no color".
+fn empty_hex_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<HexColor>, D::Error> {
+    match <Option<String> as serde::Deserialize>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(ref hex) if hex.is_empty() => Ok(None),
+        Some(hex) => HexColor::parse(&hex).map(Some)
+            .ok_or_else(|| de::Error::custom(format!("invalid hex color: {:?}", hex))),
+    }
+}
+
+/// Fimfiction's own score of how well received a story is, out of 100.
+/// It's derived from the story's likes and dislikes, but isn't simply their ratio
+/// (see `StoryAttributes::like_ratio` for that).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Rating(pub u32);
+
+impl Rating {
+    /// The rating as a percentage, i.e. 0 through 100.
+    pub fn as_percent(&self) -> u32 {
+        self.0
+    }
+    /// The rating as a fraction, i.e. 0.0 through 1.0.
+    pub fn as_fraction(&self) -> f32 {
+        self.0 as f32 / 100.0
+    }
+}
+
+/// Source (i.e. non-HTML) form of user-authored text, like a chapter or blog post body.
+/// Fimfiction sends either the BBCode directly, or an object holding it under `bbcode`;
+/// both are accepted, and any other keys in the object are kept in `other`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(from = "ContentRepr")]
+pub struct Content {
+    /// BBCode source of the text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbcode: Option<String>,
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ContentRepr {
+    Source(String),
+    Object {
+        #[serde(default)]
+        bbcode: Option<String>,
+        #[serde(flatten)]
+        other: HashMap<String, Value>,
+    },
+}
+impl From<ContentRepr> for Content {
+    fn from(repr: ContentRepr) -> Self {
+        match repr {
+            ContentRepr::Source(bbcode) => Content { bbcode: Some(bbcode), other: HashMap::new() },
+            ContentRepr::Object { bbcode, other } => Content { bbcode, other },
+        }
+    }
+}
 /// Links to where the cover image for a story may be found.
 /// One link per each size of the story.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CoverImage {
-    #[serde(with = "url_serde")]
     pub thumbnail: Url,
-    #[serde(with = "url_serde")]
     pub medium: Url,
-    #[serde(with = "url_serde")]
     pub large: Url,
-    #[serde(with = "url_serde")]
     pub full: Url,
 }
+impl CoverImage {
+    /// Link to the image of the given size.
+    pub fn url(&self, size: CoverSize) -> &Url {
+        match size {
+            CoverSize::Thumbnail => &self.thumbnail,
+            CoverSize::Medium => &self.medium,
+            CoverSize::Large => &self.large,
+            CoverSize::Full => &self.full,
+        }
+    }
+}
+/// One of the sizes a `CoverImage` is available in, from smallest to largest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CoverSize {
+    Thumbnail,
+    Medium,
+    Large,
+    Full,
+}
 /// Bookshelf icon.
 /// It appears the bookshelf icon is glyph from a font
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Icon {
     pub name: String,
     #[serde(rename="type")]
@@ -103,14 +516,15 @@ pub struct Icon {
 }
 
 /// Position of a author's note.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all="snake_case")]
 pub enum Position {
     Top,
     Bottom,
 }
 /// Privacy settings for a story
-#[derive(Debug, Deserialize, Serialize)]
+/// Ordered by how widely visible the story is, i.e. `Private < Unlisted < Public`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 #[serde(rename_all="snake_case")]
 pub enum Privacy {
     Private,
@@ -118,44 +532,134 @@ pub enum Privacy {
     Public
 }
 
-/// Story publish status
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all="snake_case")]
-pub enum PublishStatus {
-    Visible,
-    NotVisible,
-    ApproveQueue,
-    PostQueue,
+/// Declare an enum of the strings fimfiction uses for some attribute.
+/// Strings this crate doesn't know (yet) are kept in an `Unknown` variant rather than failing
+/// the whole response, so that fimfiction adding e.g. a new tag type doesn't break parsing.
+macro_rules! string_enum {
+    ($(#[$attr:meta])* $name:ident { $($variant:ident => $value:expr,)* }) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+        #[serde(from = "String", into = "String")]
+        pub enum $name {
+            $($variant,)*
+            /// Value not known to this crate, exactly as fimfiction sent it.
+            Unknown(String),
+        }
+        impl $name {
+            /// The string fimfiction uses for this value, e.g. in query parameters.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $value,)*
+                    $name::Unknown(value) => value,
+                }
+            }
+        }
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                let known = match value.as_str() {
+                    $($value => Some($name::$variant),)*
+                    _ => None,
+                };
+                known.unwrap_or_else(|| $name::Unknown(value))
+            }
+        }
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                match value {
+                    $name::Unknown(value) => value,
+                    known => known.as_str().to_owned(),
+                }
+            }
+        }
+    };
 }
-/// Story completion status
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all="snake_case")]
-pub enum CompletionStatus {
-    Incomplete,
-    Complete,
-    Hiatus,
-    Cancelled,
+
+string_enum!(
+    /// Story publish status
+    PublishStatus {
+        Visible => "visible",
+        NotVisible => "not_visible",
+        ApproveQueue => "approve_queue",
+        PostQueue => "post_queue",
+    }
+);
+string_enum!(
+    /// Story completion status
+    CompletionStatus {
+        Incomplete => "incomplete",
+        Complete => "complete",
+        Hiatus => "hiatus",
+        Cancelled => "cancelled",
+    }
+);
+string_enum!(
+    /// Story content rating
+    /// Ordered by maturity, i.e. `Everyone < Teen < Mature`, so the most mature of several
+    /// ratings is their `max()`. Unknown ratings sort after all known ones (by their string).
+    ContentRating {
+        Everyone => "everyone",
+        Teen => "teen",
+        Mature => "mature",
+    }
+);
+impl ContentRating {
+    /// Position in the ordering by maturity.
+    fn rank(&self) -> u8 {
+        match self {
+            ContentRating::Everyone => 0,
+            ContentRating::Teen => 1,
+            ContentRating::Mature => 2,
+            ContentRating::Unknown(_) => 3,
+        }
+    }
+    /// Whether this is `Mature`.
+    /// Ratings unknown to this crate are neither mature nor teen, so a filter meant to keep
+    /// mature stories out has to decide for itself what to do with `Unknown`.
+    pub fn is_mature(&self) -> bool {
+        *self == ContentRating::Mature
+    }
+    /// Whether this is `Teen` or `Mature`.
+    pub fn is_teen_or_above(&self) -> bool {
+        match self {
+            ContentRating::Teen | ContentRating::Mature => true,
+            ContentRating::Everyone | ContentRating::Unknown(_) => false,
+        }
+    }
 }
-/// Story content rating
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all="snake_case")]
-pub enum ContentRating {
-    Everyone,
-    Teen,
-    Mature,
+impl PartialOrd for ContentRating {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
-/// Story tag type
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all="snake_case")]
-pub enum TagType {
-    Character,
-    Genre,
-    Rating,
-    Content,
-    Series,
-    Warning,
-    Universe,
+impl Ord for ContentRating {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (ContentRating::Unknown(a), ContentRating::Unknown(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
 }
+string_enum!(
+    /// Tag attached to a blog post.
+    BlogTag {
+        Announcement => "announcement",
+        Contest => "contest",
+        Event => "event",
+        News => "news",
+    }
+);
+string_enum!(
+    /// Story tag type
+    TagType {
+        Character => "character",
+        Genre => "genre",
+        Rating => "rating",
+        Content => "content",
+        Series => "series",
+        Warning => "warning",
+        Universe => "universe",
+    }
+);
 
 
 
@@ -168,9 +672,8 @@ pub struct BlogPostAttributes {
     pub date_posted: DateTime<Utc>,
     /// HTML marked up truncated intro of the post
     pub intro: Option<String>,
-    ///// Content of the blog post
-    // TODO: object
-    //content: Option<String>,
+    /// Content of the blog post, if available.
+    pub content: Option<Content>,
     /// HTML version of content
     pub content_html: Option<String>,
     /// Number of views the blog post has
@@ -180,26 +683,26 @@ pub struct BlogPostAttributes {
     /// Whether the post is a site post or not
     pub site_post: bool,
     /// The site post tag of this post. Only returned if site_post is true
-    // TODO: Should this be `TagType'?
-    pub site_post_tag: Option<String>,
+    pub site_post_tag: Option<BlogTag>,
     /// Array of tags on this blog post
-    // TODO: Should this be `TagType'?
-    pub tags: Vec<String>,
+    pub tags: Vec<BlogTag>,
 }
 #[derive(Debug, Deserialize, Serialize)]
 pub struct BlogPostRelationships {
     pub author: Data<ResourceId>,
-    pub tagged_story: Data<ResourceId>,
+    /// `None` if the post isn't tagged with a story.
+    pub tagged_story: Data<Option<ResourceId>>,
 }
-pub type BlogPost = TypedResource<BlogPostAttributes, BlogPostRelationships>;
+pub type BlogPost = TypedResource<BlogPostId, BlogPostAttributes, BlogPostRelationships>;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct BookshelfAttributes {
     pub name: String,
     pub privacy: Privacy,
     pub description: String,
-    // TODO: dedicated 'color' type?
-    pub color: String,
+    /// `None` if the bookshelf has no color set.
+    #[serde(default, deserialize_with = "empty_hex_color")]
+    pub color: Option<HexColor>,
     pub icon: Icon,
     pub num_stories: u32,
     pub num_unread: u32,
@@ -215,9 +718,10 @@ pub struct BookshelfRelationships {
     // TODO: 'user' relationship wasn't documented, but is present for /api/v2/bookshelves/16299
     pub user: Data<ResourceId>,
     // TODO: 'story' relationship was documented, but not present for /api/v2/bookshelves/16299
+    // Use Application::bookshelf_stories instead.
     //pub story: Data<ResourceId>,
 }
-pub type Bookshelf = TypedResource<BookshelfAttributes, BookshelfRelationships>;
+pub type Bookshelf = TypedResource<BookshelfId, BookshelfAttributes, BookshelfRelationships>;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ChapterAttributes {
@@ -225,12 +729,14 @@ pub struct ChapterAttributes {
     pub title: String,
     pub published: bool,
     pub num_views: u32,
-    pub date_published: DateTime<Utc>,
+    #[serde(default, with = "lenient_datetime")]
+    pub date_published: Option<DateTime<Utc>>,
     pub date_modified: DateTime<Utc>,
-    pub content: Option<String>,
+    /// Source of the chapter text, if available.
+    pub content: Option<Content>,
     pub content_html: Option<String>,
-    // TODO:
-    //pub authors_note: Option<object>
+    /// Source of the author's note; `None` if the chapter has no note.
+    pub authors_note: Option<Content>,
     pub authors_note_html: Option<String>,
     pub authors_note_position: Position,
 }
@@ -238,7 +744,22 @@ pub struct ChapterAttributes {
 pub struct ChapterRelationships {
     pub story: Data<ResourceId>,
 }
-pub type Chapter = TypedResource<ChapterAttributes, ChapterRelationships>;
+pub type Chapter = TypedResource<ChapterId, ChapterAttributes, ChapterRelationships>;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommentAttributes {
+    /// Source of the comment, if available.
+    #[serde(default)]
+    pub content: Option<Content>,
+    pub content_html: String,
+    pub date_posted: DateTime<Utc>,
+}
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommentRelationships {
+    pub author: Data<ResourceId>,
+}
+/// Comment on a story or blog post.
+pub type Comment = TypedResource<CommentId, CommentAttributes, CommentRelationships>;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct FollowAttributes {
@@ -249,7 +770,7 @@ pub struct FollowRelationships {
     pub user: Data<ResourceId>,
     pub following: Data<ResourceId>,
 }
-pub type Follow = TypedResource<FollowAttributes, FollowRelationships>;
+pub type Follow = TypedResource<FollowId, FollowAttributes, FollowRelationships>;
 
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -265,13 +786,15 @@ pub struct GroupAttributes {
     pub date_created: DateTime<Utc>,
 
     // Undocumented:
+    /// Empty if it couldn't be parsed (see `TypedApiResponse::warnings`).
+    #[serde(deserialize_with = "undocumented")]
     pub icon: Avatar,
 }
 #[derive(Debug, Deserialize, Serialize)]
 pub struct GroupRelationships {
     pub founder: Data<ResourceId>,
 }
-pub type Group = TypedResource<GroupAttributes, GroupRelationships>;
+pub type Group = TypedResource<GroupId, GroupAttributes, GroupRelationships>;
 
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -289,7 +812,7 @@ pub struct GroupThreadRelationships {
     pub group: Data<ResourceId>,
     pub last_poster: Data<ResourceId>,
 }
-pub type GroupThread = TypedResource<GroupThreadAttributes, GroupThreadRelationships>;
+pub type GroupThread = TypedResource<GroupThreadId, GroupThreadAttributes, GroupThreadRelationships>;
 
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -303,10 +826,12 @@ pub struct PrivateMessageAttributes {
 }
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PrivateMessageRelationships {
-    pub sender: Data<UserAttributes>,
-    pub receiver: Data<UserAttributes>,
+    /// Only a reference to the user; include "sender" to get the user itself.
+    pub sender: Data<ResourceId>,
+    /// Only a reference to the user; include "receiver" to get the user itself.
+    pub receiver: Data<ResourceId>,
 }
-pub type PrivateMessage = TypedResource<PrivateMessageAttributes, PrivateMessageRelationships>;
+pub type PrivateMessage = TypedResource<PrivateMessageId, PrivateMessageAttributes, PrivateMessageRelationships>;
 
 /// Data fimfiction returns about any single story.
 /// See https://www.fimfiction.net/developers/api/v2/docs/resources#story
@@ -320,18 +845,21 @@ pub struct StoryAttributes {
     pub description: String,
     /// HTML version of description
     pub description_html: String,
-    /// Whether the story is published or not. Effectively the same as checking if status = visible	
+    /// Whether the story is published or not. Effectively the same as checking if status = visible
     pub published: bool,
     /// The publish status of the story
     pub status: PublishStatus,
     /// Whether the story has been submitted or not. Set to true to submit the story
     pub submitted: bool,
-    /// Date the story was first published
-    pub date_published: DateTime<Utc>,
+    /// Date the story was first published; `None` if it never has been.
+    #[serde(default, with = "lenient_datetime")]
+    pub date_published: Option<DateTime<Utc>>,
     /// Date the story was last modified. Updated whenever any edit is made to the story
     pub date_modified: DateTime<Utc>,
     /// Date the story was last updated. Only updated when a chapter is added and only if the last bump timing was more than 12 hours ago.
-    pub date_updated: DateTime<Utc>,
+    /// `None` for stories which were never updated (e.g. drafts).
+    #[serde(default, with = "lenient_datetime")]
+    pub date_updated: Option<DateTime<Utc>>,
     /// Number of views the story has (max on one chapter) 
     pub num_views: u32,
     /// Total number of views the story has (across all chapters)
@@ -346,23 +874,63 @@ pub struct StoryAttributes {
     pub cover_image: CoverImage,
 
     // undocumented attributes below
+    // Those with a sensible default fall back to it if they can't be parsed
+    // (see `TypedApiResponse::warnings`); the enums already accept any string.
+    /// Not always consistent with the story's actual chapters (e.g. it may lag behind when they
+    /// change); see `FimfictionApi::verify_chapter_count`.
+    #[serde(deserialize_with = "undocumented")]
     pub num_chapters: u32,
-    pub rating: u32,
+    #[serde(deserialize_with = "undocumented")]
+    pub rating: Rating,
     pub completion_status: CompletionStatus,
     pub content_rating: ContentRating,
+    #[serde(deserialize_with = "undocumented")]
     pub num_likes: u32,
+    #[serde(deserialize_with = "undocumented")]
     pub num_dislikes: u32,
 }
+impl StoryAttributes {
+    /// Whether the story is rated `Mature`; see `ContentRating::is_mature`.
+    pub fn is_mature(&self) -> bool {
+        self.content_rating.is_mature()
+    }
+    /// Number of likes and dislikes combined.
+    pub fn total_votes(&self) -> u32 {
+        self.num_likes + self.num_dislikes
+    }
+    /// Fraction of votes which are likes, from 0.0 to 1.0. `None` if nobody has voted.
+    pub fn like_ratio(&self) -> Option<f32> {
+        match self.total_votes() {
+            0 => None,
+            total => Some(self.num_likes as f32 / total as f32),
+        }
+    }
+}
 #[derive(Debug, Deserialize, Serialize)]
 pub struct StoryRelationships {
     pub author: Data<ResourceId>,
     // TODO: this field isn't showing up on /stories/:id requests
     //pub chapters: Data<Vec<ResourceId>>,
     pub tags: Data<Vec<ResourceId>>,
-    // TODO: this field isn't showing up on /stories/:id requests
-    //pub prequel: Data<ResourceId>,
+    /// Story this one follows on from. Only sent for some stories (and `data` is null for
+    /// stories without one), so both levels may be `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prequel: Option<Data<Option<ResourceId>>>,
+    /// Story following on from this one; see `prequel`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequel: Option<Data<Option<ResourceId>>>,
 }
-pub type Story = TypedResource<StoryAttributes, StoryRelationships>;
+impl StoryRelationships {
+    /// Reference to the prequel, if the story is known to have one.
+    pub fn prequel(&self) -> Option<&ResourceId> {
+        self.prequel.as_ref()?.data.as_ref()
+    }
+    /// Reference to the sequel, if the story is known to have one.
+    pub fn sequel(&self) -> Option<&ResourceId> {
+        self.sequel.as_ref()?.data.as_ref()
+    }
+}
+pub type Story = TypedResource<StoryId, StoryAttributes, StoryRelationships>;
 
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -373,7 +941,7 @@ pub struct StoryTagAttributes {
     pub type_: TagType,
     pub num_stories: u32,
 }
-pub type StoryTag = TypedResource<StoryTagAttributes, ()>;
+pub type StoryTag = TypedResource<StoryTagId, StoryTagAttributes, ()>;
 
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -393,17 +961,23 @@ pub struct UserAttributes {
     // undocumented
     pub color: Color,
     // Doesn't seem to be present when accessed from a story's 'included' resources
+    #[serde(default, with = "lenient_datetime")]
     pub date_last_online: Option<DateTime<Utc>>,
 }
-pub type User = TypedResource<UserAttributes, ()>;
+pub type User = TypedResource<UserId, UserAttributes, ()>;
 
 
+/// A resource of any type, told apart by its 'type' field.
+// Variants' sizes vary widely (a `Story` takes well over a kilobyte), but they're kept unboxed
+// so that they can be matched on directly.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(tag="type", rename_all="snake_case")]
 pub enum Resource {
     BlogPost(BlogPost),
     Bookshelf(Bookshelf),
     Chapter(Chapter),
+    Comment(Comment),
     Follow(Follow),
     Group(Group),
     GroupThread(GroupThread),
@@ -412,3 +986,88 @@ pub enum Resource {
     StoryTag(StoryTag),
     User(User),
 }
+
+impl Resource {
+    /// The value of the 'type' field this resource was tagged with.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Resource::BlogPost(_) => "blog_post",
+            Resource::Bookshelf(_) => "bookshelf",
+            Resource::Chapter(_) => "chapter",
+            Resource::Comment(_) => "comment",
+            Resource::Follow(_) => "follow",
+            Resource::Group(_) => "group",
+            Resource::GroupThread(_) => "group_thread",
+            Resource::PrivateMessage(_) => "private_message",
+            Resource::Story(_) => "story",
+            Resource::StoryTag(_) => "story_tag",
+            Resource::User(_) => "user",
+        }
+    }
+    /// The id of this resource, regardless of its type.
+    pub fn id(&self) -> u32 {
+        match self {
+            Resource::BlogPost(r) => r.id.as_u32(),
+            Resource::Bookshelf(r) => r.id.as_u32(),
+            Resource::Chapter(r) => r.id.as_u32(),
+            Resource::Comment(r) => r.id.as_u32(),
+            Resource::Follow(r) => r.id.as_u32(),
+            Resource::Group(r) => r.id.as_u32(),
+            Resource::GroupThread(r) => r.id.as_u32(),
+            Resource::PrivateMessage(r) => r.id.as_u32(),
+            Resource::Story(r) => r.id.as_u32(),
+            Resource::StoryTag(r) => r.id.as_u32(),
+            Resource::User(r) => r.id.as_u32(),
+        }
+    }
+    /// Whether `id` refers to this resource.
+    pub fn is(&self, id: &ResourceId) -> bool {
+        self.type_name() == id.type_ && self.id() == id.id
+    }
+}
+
+/// Define, for each variant of `Resource`, methods extracting the typed resource it holds.
+macro_rules! resource_conversions {
+    ($($variant:ident: $as_name:ident, $into_name:ident,)*) => {
+        impl Resource {
+            $(
+                #[doc = concat!("The `", stringify!($variant), "` this is, if it's one.")]
+                pub fn $as_name(&self) -> Option<&$variant> {
+                    match self {
+                        Resource::$variant(r) => Some(r),
+                        _ => None,
+                    }
+                }
+                #[doc = concat!("The `", stringify!($variant), "` this is, or else the resource itself.")]
+                #[allow(clippy::result_large_err)]
+                pub fn $into_name(self) -> Result<$variant, Resource> {
+                    match self {
+                        Resource::$variant(r) => Ok(r),
+                        other => Err(other),
+                    }
+                }
+            )*
+        }
+        $(
+            impl From<$variant> for Resource {
+                fn from(r: $variant) -> Self {
+                    Resource::$variant(r)
+                }
+            }
+        )*
+    };
+}
+
+resource_conversions!(
+    BlogPost: as_blog_post, into_blog_post,
+    Bookshelf: as_bookshelf, into_bookshelf,
+    Chapter: as_chapter, into_chapter,
+    Comment: as_comment, into_comment,
+    Follow: as_follow, into_follow,
+    Group: as_group, into_group,
+    GroupThread: as_group_thread, into_group_thread,
+    PrivateMessage: as_private_message, into_private_message,
+    Story: as_story, into_story,
+    StoryTag: as_story_tag, into_story_tag,
+    User: as_user, into_user,
+);
@@ -0,0 +1,362 @@
+//! Top-level documents returned by the API, shared by `Application` and `AsyncApplication`.
+#[cfg(any(feature = "blocking", feature = "async"))]
+use reqwest::header::{self, HeaderMap};
+use reqwest::{StatusCode, Url};
+#[cfg(any(feature = "blocking", feature = "async"))]
+use serde::de::DeserializeOwned;
+use serde::de::{self, Deserializer};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+use crate::error::ApiError;
+use crate::query::Cursor;
+use crate::resources::{
+    warnings, BlogPost, Bookshelf, Chapter, Comment, Follow, Group, GroupThread, PrivateMessage,
+    Resource, ResourceId, Story, StoryTag, User,
+};
+
+#[derive(Debug)]
+pub struct ApiResponse {
+    pub data: Resource,
+    pub included: Vec<Resource>,
+    // NB: Url relative to fimfiction.net; cannot use Url type for that.
+    pub uri: String,
+    pub method: String,
+    /// Absent if fimfiction didn't send a debug block.
+    pub debug: Option<DebugInfo>,
+    /// Links to neighboring pages, when the response is a page of a collection.
+    pub links: PageLinks,
+    pub meta: ResponseMeta,
+    /// Problems noticed while parsing the response, which didn't stop it from succeeding.
+    /// Not part of the response itself.
+    pub warnings: Vec<String>,
+}
+#[derive(Debug)]
+pub struct TypedApiResponse<T> {
+    pub data: T,
+    pub included: Vec<Resource>,
+    // NB: Url relative to fimfiction.net; cannot use Url type for that.
+    pub uri: String,
+    pub method: String,
+    /// Absent if fimfiction didn't send a debug block.
+    pub debug: Option<DebugInfo>,
+    /// Links to neighboring pages, when the response is a page of a collection.
+    pub links: PageLinks,
+    pub meta: ResponseMeta,
+    /// Problems noticed while parsing the response, which didn't stop it from succeeding,
+    /// e.g. an undocumented attribute which had to be replaced by its default.
+    /// Useful to detect changes to the API. Not part of the response itself.
+    pub warnings: Vec<String>,
+}
+/// The response types, as sent, minus the warnings recorded while deserializing them.
+#[derive(Deserialize)]
+struct ResponseDocument<T> {
+    data: T,
+    included: Vec<Resource>,
+    uri: String,
+    method: String,
+    #[serde(default, deserialize_with = "debug_block")]
+    debug: Option<DebugInfo>,
+    #[serde(default)]
+    links: PageLinks,
+    #[serde(default)]
+    meta: ResponseMeta,
+}
+/// A disabled `debug` block may be sent as null or an empty array (rather than left out),
+/// neither of which is a `DebugInfo`.
+fn debug_block<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DebugInfo>, D::Error> {
+    match <Value as serde::Deserialize>::deserialize(deserializer)? {
+        Value::Null => Ok(None),
+        Value::Array(ref items) if items.is_empty() => Ok(None),
+        other => serde_json::from_value(other).map(Some).map_err(de::Error::custom),
+    }
+}
+impl<'de> serde::Deserialize<'de> for ApiResponse {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (doc, warnings) = warnings::collect(|| ResponseDocument::<Resource>::deserialize(deserializer));
+        let doc = doc?;
+        Ok(ApiResponse {
+            data: doc.data,
+            included: doc.included,
+            uri: doc.uri,
+            method: doc.method,
+            debug: doc.debug,
+            links: doc.links,
+            meta: doc.meta,
+            warnings,
+        })
+    }
+}
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for TypedApiResponse<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (doc, warnings) = warnings::collect(|| ResponseDocument::<T>::deserialize(deserializer));
+        let doc = doc?;
+        Ok(TypedApiResponse {
+            data: doc.data,
+            included: doc.included,
+            uri: doc.uri,
+            method: doc.method,
+            debug: doc.debug,
+            links: doc.links,
+            meta: doc.meta,
+            warnings,
+        })
+    }
+}
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl<T: Default> TypedApiResponse<T> {
+    /// Response with no data, for requests which can be answered without asking fimfiction.
+    pub(crate) fn empty(uri: String) -> Self {
+        TypedApiResponse {
+            data: T::default(),
+            included: Vec::new(),
+            uri,
+            method: "GET".to_owned(),
+            debug: None,
+            links: PageLinks::default(),
+            meta: ResponseMeta::default(),
+            warnings: Vec::new(),
+        }
+    }
+}
+/// Top-level `links` of a response.
+/// Each page link is absent if there is no such page (e.g. `next` on the last page).
+#[derive(Debug, Default, Deserialize)]
+pub struct PageLinks {
+    /// Absolute URL of the requested document itself.
+    #[serde(rename = "self", default)]
+    pub self_: Option<Url>,
+    pub first: Option<Url>,
+    pub prev: Option<Url>,
+    pub next: Option<Url>,
+    pub last: Option<Url>,
+}
+/// Top-level `debug` of a response: diagnostics about how the server handled the request.
+#[derive(Debug, Default, Deserialize)]
+pub struct DebugInfo {
+    /// Time taken to handle the request, as sent, e.g. "10.33ms". See `DebugInfo::duration`.
+    #[serde(default)]
+    pub duration: Option<String>,
+    /// Number of database queries made, if fimfiction says.
+    #[serde(default)]
+    pub query_count: Option<u32>,
+    /// What the request cost, in terms of the rate limit, if fimfiction says.
+    #[serde(default)]
+    pub cost: Option<f64>,
+    /// Any other keys, which aren't modelled (yet).
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+impl DebugInfo {
+    /// Time taken to handle the request, if `duration` was sent and could be parsed.
+    pub fn duration(&self) -> Option<Duration> {
+        let duration = self.duration.as_ref()?.trim();
+        let (number, micros_per_unit) = if let Some(number) = duration.strip_suffix("ms") {
+            (number, 1e3)
+        } else if let Some(number) = duration.strip_suffix('s') {
+            (number, 1e6)
+        } else {
+            return None;
+        };
+        let micros = number.trim().parse::<f64>().ok()? * micros_per_unit;
+        if micros.is_finite() && micros >= 0.0 {
+            Some(Duration::from_micros(micros.round() as u64))
+        } else {
+            None
+        }
+    }
+}
+/// Top-level `meta` of a response.
+#[derive(Debug, Default, Deserialize)]
+pub struct ResponseMeta {
+    /// Number of resources in the whole collection, when the response is a page of one.
+    #[serde(default)]
+    pub total: Option<u64>,
+    /// Any other keys, which aren't modelled (yet).
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+impl ResponseMeta {
+    /// Number of pages the collection spans, if each holds `page_size` resources.
+    pub fn num_pages(&self, page_size: u32) -> Option<u64> {
+        if page_size == 0 {
+            return None;
+        }
+        let page_size = u64::from(page_size);
+        self.total.map(|total| total.div_ceil(page_size))
+    }
+}
+/// Parsed response, alongside the JSON it was parsed from.
+/// Useful to get at fields the response types don't model (yet).
+#[derive(Debug)]
+pub struct WithRaw<T> {
+    pub parsed: T,
+    pub raw: Value,
+}
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl<T: DeserializeOwned> WithRaw<T> {
+    pub(crate) fn parse(raw: Value) -> Result<Self, ApiError> {
+        let parsed = <T as serde::Deserialize>::deserialize(&raw).map_err(ApiError::Json)?;
+        Ok(WithRaw { parsed, raw })
+    }
+}
+/// Opaque identifier of one version of a resource, as sent in the ETag header.
+/// Can be persisted (via `as_str`) and later passed back to e.g. `Application::story_if_changed`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ETag(String);
+impl ETag {
+    /// Wrap an ETag previously obtained from `as_str`. Should include its quotes.
+    pub fn new<S: Into<String>>(etag: S) -> Self {
+        ETag(etag.into())
+    }
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+    /// ETag of the response with these headers, if it has one.
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        headers.get(header::ETAG)?.to_str().ok().map(ETag::new)
+    }
+}
+/// Result of a request made conditional on the resource having changed.
+#[derive(Debug)]
+pub enum Conditional<T> {
+    /// The resource differs from the version identified by the given ETag (or none was given).
+    Modified(WithETag<T>),
+    /// The resource is still the version identified by the given ETag.
+    NotModified,
+}
+/// Parsed response, alongside the ETag identifying that version of it, if the server sent one.
+#[derive(Debug)]
+pub struct WithETag<T> {
+    pub value: T,
+    pub etag: Option<ETag>,
+}
+/// Parsed response, alongside its status, e.g. to tell 201 Created apart from 200 OK after a write.
+#[derive(Debug)]
+pub struct WithStatus<T> {
+    pub value: T,
+    pub status: StatusCode,
+}
+
+pub type BlogPostResponse = TypedApiResponse<BlogPost>;
+pub type BlogPostsResponse = TypedApiResponse<Vec<BlogPost>>;
+pub type BookshelfResponse = TypedApiResponse<Bookshelf>;
+pub type BookshelvesResponse = TypedApiResponse<Vec<Bookshelf>>;
+pub type ChapterResponse = TypedApiResponse<Chapter>;
+pub type ChaptersResponse = TypedApiResponse<Vec<Chapter>>;
+pub type CommentsResponse = TypedApiResponse<Vec<Comment>>;
+pub type FollowResponse = TypedApiResponse<Follow>;
+pub type FollowersResponse = TypedApiResponse<Vec<Follow>>;
+pub type GroupResponse = TypedApiResponse<Group>;
+pub type GroupThreadsResponse = TypedApiResponse<Vec<GroupThread>>;
+pub type PrivateMessageResponse = TypedApiResponse<PrivateMessage>;
+pub type PrivateMessagesResponse = TypedApiResponse<Vec<PrivateMessage>>;
+pub type StoriesResponse = TypedApiResponse<Vec<Story>>;
+pub type StoryResponse = TypedApiResponse<Story>;
+pub type StoryTagResponse = TypedApiResponse<StoryTag>;
+pub type StoryTagsResponse = TypedApiResponse<Vec<StoryTag>>;
+pub type UserResponse = TypedApiResponse<User>;
+pub type UsersResponse = TypedApiResponse<Vec<User>>;
+
+impl ApiResponse {
+    /// Find the resource `id` refers to within the `included` section of this response.
+    /// Only resources requested via `include` will be found.
+    pub fn resolve(&self, id: &ResourceId) -> Option<&Resource> {
+        resolve(&self.included, id)
+    }
+    /// Index the `included` section by id, for resolving many references without a scan each.
+    pub fn included_index(&self) -> IncludedIndex<'_> {
+        IncludedIndex::new(&self.included)
+    }
+    /// Absolute URL of this document (`links.self`), unlike the relative `uri`.
+    /// Differs from the URL which was requested if the request was redirected.
+    pub fn self_link(&self) -> Option<&Url> {
+        self.links.self_.as_ref()
+    }
+}
+impl<T> TypedApiResponse<T> {
+    /// Where to continue from to retrieve the next page (see `Application::user_followers_from`),
+    /// or `None` if this is the last one.
+    pub fn next_cursor(&self) -> Option<Cursor> {
+        self.links.next.as_ref().and_then(Cursor::from_link)
+    }
+    /// Find the resource `id` refers to within the `included` section of this response.
+    /// Only resources requested via `include` will be found.
+    pub fn resolve(&self, id: &ResourceId) -> Option<&Resource> {
+        resolve(&self.included, id)
+    }
+    /// Index the `included` section by id, for resolving many references without a scan each.
+    pub fn included_index(&self) -> IncludedIndex<'_> {
+        IncludedIndex::new(&self.included)
+    }
+    /// Absolute URL of this document (`links.self`), unlike the relative `uri`.
+    /// Differs from the URL which was requested if the request was redirected.
+    pub fn self_link(&self) -> Option<&Url> {
+        self.links.self_.as_ref()
+    }
+}
+
+fn resolve<'a>(included: &'a [Resource], id: &ResourceId) -> Option<&'a Resource> {
+    included.iter().find(|res| res.is(id))
+}
+
+/// The included resources of a response, keyed by type and id; see `TypedApiResponse::included_index`.
+#[derive(Clone, Debug)]
+pub struct IncludedIndex<'a> {
+    by_id: HashMap<(&'static str, u32), &'a Resource>,
+}
+
+impl<'a> IncludedIndex<'a> {
+    pub fn new(included: &'a [Resource]) -> Self {
+        let by_id = included.iter().map(|res| ((res.type_name(), res.id()), res)).collect();
+        IncludedIndex { by_id }
+    }
+    /// Same as `TypedApiResponse::resolve`, without scanning the included resources.
+    pub fn resolve(&self, id: &ResourceId) -> Option<&'a Resource> {
+        self.by_id.get(&(id.type_.as_str(), id.id)).copied()
+    }
+    pub fn len(&self) -> usize {
+        self.by_id.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+}
+
+/// Define methods on both response types, each iterating over the included resources of one type.
+macro_rules! included_accessors {
+    ($($name:ident: $variant:ident,)*) => {
+        impl ApiResponse {
+            $(included_accessors!(@method $name, $variant);)*
+        }
+        impl<T> TypedApiResponse<T> {
+            $(included_accessors!(@method $name, $variant);)*
+        }
+    };
+    (@method $name:ident, $variant:ident) => {
+        #[doc = concat!("The included resources of type `", stringify!($variant), "`.")]
+        pub fn $name(&self) -> impl Iterator<Item = &$variant> {
+            self.included.iter().filter_map(|res| match res {
+                Resource::$variant(r) => Some(r),
+                _ => None,
+            })
+        }
+    };
+}
+
+included_accessors!(
+    blog_posts: BlogPost,
+    bookshelves: Bookshelf,
+    chapters: Chapter,
+    comments: Comment,
+    follows: Follow,
+    groups: Group,
+    group_threads: GroupThread,
+    private_messages: PrivateMessage,
+    stories: Story,
+    tags: StoryTag,
+    users: User,
+);
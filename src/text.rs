@@ -0,0 +1,26 @@
+//! Plain text versions of the HTML fields, like `StoryAttributes::description_html`.
+//! Requires the `text` feature.
+use html_escape::decode_html_entities;
+
+/// Strip the tags from `html` and decode its entities (e.g. "&amp;" becomes "&").
+/// Line breaks and the ends of paragraphs become newlines; no other formatting survives.
+pub fn html_to_text(html: &str) -> String {
+    let mut stripped = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        stripped.push_str(&rest[..start]);
+        let (tag, after) = match rest[start..].find('>') {
+            Some(end) => (&rest[start + 1..start + end], &rest[start + end + 1..]),
+            // An unclosed tag runs to the end of the input.
+            None => (&rest[start + 1..], ""),
+        };
+        let name = tag.trim_end_matches('/').split_whitespace().next().unwrap_or("");
+        if name.eq_ignore_ascii_case("br") || name.eq_ignore_ascii_case("/p") {
+            stripped.push('\n');
+        }
+        rest = after;
+    }
+    stripped.push_str(rest);
+    // Decoded last, so that an escaped "&lt;" isn't mistaken for the start of a tag.
+    decode_html_entities(stripped.trim_end()).into_owned()
+}
@@ -0,0 +1,256 @@
+//! Request bodies for the endpoints which create or modify resources.
+//! Each is sent as a JSON:API document; see https://jsonapi.org/format/#crud
+#[cfg(any(feature = "blocking", feature = "async"))]
+use serde::Serialize;
+#[cfg(any(feature = "blocking", feature = "async"))]
+use std::collections::HashMap;
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+use crate::resources::{Data, ResourceId};
+use crate::resources::{ContentRating, HexColor, Icon, Position, Privacy};
+
+/// Media type of JSON:API documents, sent in the Accept header of every request
+/// and the Content-Type of every request body.
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) const MEDIA_TYPE: &str = "application/vnd.api+json";
+
+/// Top-level document sent as the body of a POST or PATCH.
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Debug, Serialize)]
+pub(crate) struct Document<A: Serialize> {
+    pub data: DocumentData<A>,
+}
+
+/// Primary data of a `Document`: the resource being created or updated.
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Debug, Serialize)]
+pub(crate) struct DocumentData<A: Serialize> {
+    #[serde(rename = "type")]
+    pub type_: &'static str,
+    /// Absent when creating a resource, since the server assigns the id.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub attributes: A,
+    /// Other resources to associate this one with, keyed by relationship name.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub relationships: HashMap<&'static str, Data<ResourceId>>,
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl<A: Serialize> Document<A> {
+    /// Document creating a new resource of type `type_`.
+    pub fn create(type_: &'static str, attributes: A) -> Self {
+        Document {
+            data: DocumentData { type_, id: None, attributes, relationships: HashMap::new() },
+        }
+    }
+    /// Document updating the resource of type `type_` with the given id.
+    pub fn update<I: ToString>(type_: &'static str, id: I, attributes: A) -> Self {
+        Document {
+            data: DocumentData {
+                type_,
+                id: Some(id.to_string()),
+                attributes,
+                relationships: HashMap::new(),
+            },
+        }
+    }
+    /// Relate the resource to the one of type `type_` with the given id, via relationship `name`.
+    pub fn relationship(mut self, name: &'static str, type_: &str, id: u32) -> Self {
+        let related = ResourceId { type_: type_.to_owned(), id };
+        self.data.relationships.insert(name, Data { data: related, links: None });
+        self
+    }
+}
+
+/// Body of a request adding resources to (or removing them from) a to-many relationship.
+/// See https://jsonapi.org/format/#crud-updating-to-many-relationships
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn relationship_members(type_: &str, ids: &[u32]) -> Data<Vec<ResourceId>> {
+    Data {
+        data: ids.iter().map(|&id| ResourceId { type_: type_.to_owned(), id }).collect(),
+        links: None,
+    }
+}
+
+/// Attributes of a follow to create, via `Application::follow_user`. It has none of its own;
+/// the user to follow is given as a relationship.
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Debug, Serialize)]
+pub(crate) struct NewFollow {}
+
+/// Attributes of a vote to cast, via `Application::rate_story`.
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Debug, Serialize)]
+pub(crate) struct NewVote {
+    /// Either "like" or "dislike".
+    pub vote: &'static str,
+}
+
+/// Attributes of a private message to send, via `Application::send_private_message`.
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Debug, Serialize)]
+pub(crate) struct NewPrivateMessage<'a> {
+    pub subject: &'a str,
+    /// BBCode source of the message.
+    pub content: &'a str,
+}
+
+/// How the authorized user rates a story, via `Application::rate_story`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StoryVote {
+    Like,
+    Dislike,
+    /// Withdraw any previous vote.
+    None,
+}
+
+/// Changes to make to a story, via `Application::update_story`.
+/// Only the attributes which were set are sent; the rest are left as they are.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct StoryPatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    submitted: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_rating: Option<ContentRating>,
+}
+
+impl StoryPatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+    /// BBCode source of the story's description.
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+    /// Set to true to submit the story for approval.
+    pub fn submitted(mut self, submitted: bool) -> Self {
+        self.submitted = Some(submitted);
+        self
+    }
+    pub fn content_rating(mut self, rating: ContentRating) -> Self {
+        self.content_rating = Some(rating);
+        self
+    }
+}
+
+/// Chapter to add to a story, via `Application::create_chapter`.
+#[derive(Clone, Debug, Serialize)]
+pub struct NewChapter {
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    authors_note: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    authors_note_position: Option<Position>,
+}
+
+impl NewChapter {
+    pub fn new<S: Into<String>>(title: S) -> Self {
+        NewChapter {
+            title: title.into(),
+            content: None,
+            authors_note: None,
+            authors_note_position: None,
+        }
+    }
+    /// BBCode source of the chapter text.
+    pub fn content<S: Into<String>>(mut self, content: S) -> Self {
+        self.content = Some(content.into());
+        self
+    }
+    /// BBCode source of the author's note.
+    pub fn authors_note<S: Into<String>>(mut self, note: S) -> Self {
+        self.authors_note = Some(note.into());
+        self
+    }
+    pub fn authors_note_position(mut self, position: Position) -> Self {
+        self.authors_note_position = Some(position);
+        self
+    }
+}
+
+/// Changes to make to a chapter, via `Application::update_chapter`.
+/// Only the attributes which were set are sent; the rest are left as they are.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ChapterPatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    authors_note: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    authors_note_position: Option<Position>,
+}
+
+impl ChapterPatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+    /// BBCode source of the chapter text.
+    pub fn content<S: Into<String>>(mut self, content: S) -> Self {
+        self.content = Some(content.into());
+        self
+    }
+    /// BBCode source of the author's note.
+    pub fn authors_note<S: Into<String>>(mut self, note: S) -> Self {
+        self.authors_note = Some(note.into());
+        self
+    }
+    pub fn authors_note_position(mut self, position: Position) -> Self {
+        self.authors_note_position = Some(position);
+        self
+    }
+}
+
+/// Bookshelf to create, via `Application::create_bookshelf`.
+#[derive(Clone, Debug, Serialize)]
+pub struct NewBookshelf {
+    name: String,
+    privacy: Privacy,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<HexColor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<Icon>,
+}
+
+impl NewBookshelf {
+    pub fn new<S: Into<String>>(name: S, privacy: Privacy) -> Self {
+        NewBookshelf {
+            name: name.into(),
+            privacy,
+            description: None,
+            color: None,
+            icon: None,
+        }
+    }
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+    pub fn color(mut self, color: HexColor) -> Self {
+        self.color = Some(color);
+        self
+    }
+    pub fn icon(mut self, icon: Icon) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+}
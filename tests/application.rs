@@ -0,0 +1,157 @@
+//! `Application` against a local stand-in for fimfiction, which only ever answers the token request.
+#![cfg(feature = "blocking")]
+use fimfiction_api::*;
+use reqwest::{StatusCode, Url};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Read a whole request (headers, then a body of Content-Length bytes), so that it can be answered.
+/// Returns whatever was read if the client goes away first.
+fn read_request(conn: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    loop {
+        let n = match conn.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request);
+        if let Some(end) = text.find("\r\n\r\n") {
+            let length = text[..end].lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_at(line.find(':')?);
+                    if name.eq_ignore_ascii_case("content-length") {
+                        value[1..].trim().parse::<usize>().ok()
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or(0);
+            if request.len() >= end + 4 + length {
+                break;
+            }
+        }
+    }
+    String::from_utf8_lossy(&request).into_owned()
+}
+
+/// A page of no stories, at its end.
+const EMPTY_STORIES: &str = r#"{"data": [], "included": [], "uri": "/api/v2/stories", "method": "GET"}"#;
+
+/// HTTP response with the given status and JSON body, closing the connection after it.
+fn response(status: &str, body: &str) -> String {
+    response_with_headers(status, "", body)
+}
+
+/// Same as `response`, with extra `headers` (each ending in "\r\n").
+fn response_with_headers(status: &str, headers: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        headers,
+        body
+    )
+}
+
+/// Successful response from the token endpoint.
+fn token() -> String {
+    response("200 OK", r#"{"access_token": "t0ken", "token_type": "Bearer"}"#)
+}
+
+/// Local stand-in for fimfiction, which answers one request with each of `responses` in turn
+/// before going away, so that any request sent afterwards fails.
+/// Returns its API root, and the text of each request it answers.
+fn server(responses: Vec<String>) -> (Url, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = Url::parse(&format!("http://{}/api/v2/", listener.local_addr().unwrap())).unwrap();
+    let (requests, received) = mpsc::channel();
+    thread::spawn(move || {
+        for response in responses {
+            let (mut conn, _) = listener.accept().unwrap();
+            let _ = requests.send(read_request(&mut conn));
+            conn.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    (base_url, received)
+}
+
+/// Application authorized by a `server`, which then answers `responses`.
+/// Also returns each request answered after the token one.
+fn serve(responses: Vec<String>) -> (Application, Receiver<String>) {
+    let (base_url, requests) = server(Some(token()).into_iter().chain(responses).collect());
+    let app = ApplicationBuilder::new()
+        .base_url(base_url)
+        .authorize_client_credentials("id", "secret")
+        .unwrap();
+    // The token request.
+    requests.recv().unwrap();
+    (app, requests)
+}
+
+#[test]
+fn no_stories_are_retrieved_without_a_request() {
+    let (app, _) = serve(Vec::new());
+    let resp = app.stories(&[]).unwrap();
+    assert!(resp.data.is_empty());
+    assert!(resp.included.is_empty());
+    // Whereas anything else can't reach the server.
+    assert!(app.stories(&[StoryId(1)]).is_err());
+}
+
+#[test]
+fn story_search_streams_from_the_first_page() {
+    let (app, requests) = serve(vec![response("200 OK", EMPTY_STORIES)]);
+    let query = SearchQuery::new().term("maps").page(Page { number: 5, size: 20 });
+    assert_eq!(app.search_stories_iter(&query).count(), 0);
+    let request = requests.recv().unwrap();
+    assert!(request.contains("page%5Bsize%5D=20"), "{}", request);
+    assert!(!request.contains("page%5Bnumber%5D"), "{}", request);
+}
+
+#[test]
+fn next_links_away_from_the_api_are_not_followed() {
+    let page = r#"{"data": [], "included": [], "uri": "/api/v2/stories", "method": "GET",
+        "links": {"next": "http://fimfiction.invalid/api/v2/stories?page[number]=2"}}"#;
+    let (app, _) = serve(vec![response("200 OK", page)]);
+    let results: Vec<_> = app.search_stories_iter(&SearchQuery::new()).collect();
+    match &results[..] {
+        [Err(ApiError::InvalidPath(path))] => assert!(path.contains("fimfiction.invalid"), "{}", path),
+        other => panic!("expected the next link to be refused, got {:?}", other),
+    }
+}
+
+#[test]
+fn applications_built_alike_dont_share_cached_responses() {
+    let story = response_with_headers("200 OK", "ETag: \"v1\"\r\n", include_str!("fixtures/story.json"));
+    let (base_url, requests) = server(vec![token(), story.clone(), token(), story]);
+    let builder = ApplicationBuilder::new().base_url(base_url).cache_capacity(8);
+
+    let first = builder.clone().authorize_client_credentials("id", "secret").unwrap();
+    first.story(StoryId(428677)).unwrap();
+    let second = builder.authorize_client_credentials("other", "secret").unwrap();
+    second.story(StoryId(428677)).unwrap();
+
+    let requests: Vec<String> = requests.iter().collect();
+    assert_eq!(requests.len(), 4);
+    // The second application has nothing cached to revalidate.
+    assert!(!requests[3].to_ascii_lowercase().contains("if-none-match"), "{}", requests[3]);
+}
+
+#[test]
+fn retries_are_abandoned_rather_than_waiting_too_long() {
+    let busy = response_with_headers("429 Too Many Requests", "Retry-After: 86400\r\n", "{}");
+    let (base_url, _requests) = server(vec![token(), busy]);
+    let app = ApplicationBuilder::new()
+        .base_url(base_url)
+        .max_retries(3)
+        .authorize_client_credentials("id", "secret")
+        .unwrap();
+    match app.story(StoryId(428677)) {
+        Err(ApiError::Status { status, .. }) => assert_eq!(status, StatusCode::TOO_MANY_REQUESTS),
+        other => panic!("expected the 429 to be returned, got {:?}", other),
+    }
+}
@@ -0,0 +1,40 @@
+//! Serving captured resources through `MockApplication`.
+#![cfg(feature = "mock")]
+use fimfiction_api::*;
+use reqwest::StatusCode;
+
+/// Code under test only needs some `FimfictionApi`, not a real `Application`.
+fn story_title(api: &dyn FimfictionApi, id: StoryId) -> Result<String, ApiError> {
+    Ok(api.story(id)?.data.attributes.title)
+}
+
+#[test]
+fn mock_serves_inserted_resources() {
+    let resp: StoryResponse = serde_json::from_str(include_str!("fixtures/story.json")).unwrap();
+    let mut mock = MockApplication::new();
+    mock.insert_story(resp.data);
+
+    assert_eq!(story_title(&mock, StoryId(428677)).unwrap(), "The Cartographer's Daughter");
+    // Each request gets its own copy.
+    assert_eq!(mock.story(StoryId(428677)).unwrap().data.attributes.num_chapters, 14);
+    match story_title(&mock, StoryId(1)) {
+        Err(ApiError::Status { status, .. }) => assert_eq!(status, StatusCode::NOT_FOUND),
+        other => panic!("expected a 404, got {:?}", other),
+    }
+}
+
+#[test]
+fn chapter_count_is_verified_against_the_mock() {
+    let story = || serde_json::from_str::<StoryResponse>(include_str!("fixtures/story.json")).unwrap().data;
+    let chapter: ChapterResponse = serde_json::from_str(include_str!("fixtures/chapter.json")).unwrap();
+    let mut mock = MockApplication::new();
+    mock.insert_story(story());
+    mock.insert_chapter(chapter.data);
+
+    assert_eq!(mock.story_chapters(StoryId(428677)).unwrap().data.len(), 1);
+    assert_eq!(
+        mock.verify_chapter_count(&story()).unwrap(),
+        Some(ChapterCountMismatch { claimed: 14, actual: 1 })
+    );
+    assert!(mock.story_chapters(StoryId(1)).is_err());
+}
@@ -0,0 +1,336 @@
+//! Deserialization of captured API responses.
+use fimfiction_api::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Check that `value` survives being serialized and parsed back.
+/// Compared as JSON, since the resources don't implement `PartialEq`.
+fn assert_roundtrips<T: Serialize + DeserializeOwned>(value: &T) {
+    let first = serde_json::to_value(value).unwrap();
+    let reparsed: T = serde_json::from_value(first.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&reparsed).unwrap(), first);
+}
+
+#[test]
+fn private_message_relationships_are_references() {
+    let resp: PrivateMessageResponse =
+        serde_json::from_str(include_str!("fixtures/private_message.json")).unwrap();
+    let relationships = resp.data.relationships.as_ref().unwrap();
+    assert_eq!(relationships.sender.data.type_, "user");
+    assert_eq!(relationships.sender.data.id, 211739);
+    assert_eq!(relationships.receiver.data.id, 33084);
+
+    // The sender was requested via `include`, so it can be resolved.
+    match resp.resolve(&relationships.sender.data) {
+        Some(Resource::User(user)) => {
+            assert_eq!(user.id, UserId(211739));
+            assert_eq!(user.attributes.name, "Pen Stroke");
+        }
+        other => panic!("sender not resolved: {:?}", other),
+    }
+    assert!(resp.resolve(&relationships.receiver.data).is_none());
+}
+
+#[test]
+fn included_index_resolves_like_resolve() {
+    let resp: StoryResponse = serde_json::from_str(include_str!("fixtures/story.json")).unwrap();
+    let index = resp.included_index();
+    assert_eq!(index.len(), resp.included.len());
+    let relationships = resp.data.relationships.as_ref().unwrap();
+    for tag in &relationships.tags.data {
+        let resolved = index.resolve(tag).unwrap();
+        assert!(resolved.is(tag));
+        assert!(std::ptr::eq(resolved, resp.resolve(tag).unwrap()));
+    }
+    assert!(index.resolve(&relationships.author.data).is_none());
+}
+
+#[test]
+fn empty_to_one_relationships_are_none() {
+    let null: Data<Option<ResourceId>> = serde_json::from_str(r#"{"data": null}"#).unwrap();
+    assert!(null.data.is_none());
+    let missing: Data<Option<ResourceId>> = serde_json::from_str("{}").unwrap();
+    assert!(missing.data.is_none());
+    let present: Data<Option<ResourceId>> =
+        serde_json::from_str(r#"{"data": {"type": "story", "id": "428677"}}"#).unwrap();
+    assert_eq!(present.data.unwrap().id, 428677);
+}
+
+#[test]
+fn private_message_roundtrips() {
+    let resp: PrivateMessageResponse =
+        serde_json::from_str(include_str!("fixtures/private_message.json")).unwrap();
+    assert_roundtrips(&resp.data);
+    assert_roundtrips(&resp.included);
+}
+
+#[test]
+fn story_roundtrips() {
+    let resp: StoryResponse = serde_json::from_str(include_str!("fixtures/story.json")).unwrap();
+    let story = &resp.data;
+    assert_eq!(story.id, StoryId(428677));
+    assert_eq!(story.attributes.rating, Rating(96));
+    assert_eq!(story.attributes.content_rating, ContentRating::Teen);
+    assert_eq!(story.attributes.completion_status, CompletionStatus::Incomplete);
+    assert_eq!(story.attributes.color.rgb, [107, 143, 179]);
+    let relationships = story.relationships.as_ref().unwrap();
+    assert_eq!(relationships.author.data.id, 211739);
+    assert_eq!(relationships.tags.data.len(), 2);
+    assert!(relationships.tags.links.as_ref().unwrap().related.is_some());
+    assert!(relationships.prequel().is_none());
+    assert_eq!(resp.tags().count(), 2);
+    assert_eq!(resp.self_link().unwrap().as_str(),
+        "https://www.fimfiction.net/api/v2/stories/428677?include=tags");
+    assert!(resp.links.next.is_none());
+    assert_eq!(resp.included[1].as_story_tag().unwrap().attributes.name, "Twilight Sparkle");
+    assert!(resp.included[1].as_user().is_none());
+    assert_roundtrips(&resp.data);
+    assert_roundtrips(&resp.included);
+}
+
+#[test]
+fn user_roundtrips() {
+    let resp: UserResponse = serde_json::from_str(include_str!("fixtures/user.json")).unwrap();
+    let user = &resp.data;
+    assert_eq!(user.id, UserId(211739));
+    assert_eq!(user.attributes.name, "Pen Stroke");
+    assert_eq!(user.attributes.avatar.sizes.len(), 5);
+    assert!(user.attributes.date_last_online.is_some());
+    assert!(user.relationships.is_none());
+    assert_roundtrips(&resp.data);
+}
+
+#[test]
+fn chapter_roundtrips() {
+    let resp: ChapterResponse = serde_json::from_str(include_str!("fixtures/chapter.json")).unwrap();
+    let chapter = &resp.data;
+    assert_eq!(chapter.id, ChapterId(1357902));
+    assert_eq!(chapter.attributes.chapter_number, 3);
+    assert_eq!(chapter.attributes.authors_note_position, Position::Bottom);
+    assert!(chapter.attributes.content.as_ref().unwrap().bbcode.is_some());
+    assert_eq!(chapter.relationships.as_ref().unwrap().story.data.id, 428677);
+    assert_roundtrips(&resp.data);
+}
+
+#[test]
+fn group_roundtrips() {
+    let resp: GroupResponse = serde_json::from_str(include_str!("fixtures/group.json")).unwrap();
+    let group = &resp.data;
+    assert_eq!(group.id, GroupId(209275));
+    assert!(group.attributes.icon.get(192).is_none());
+    assert!(group.attributes.icon.get(128).is_some());
+    assert_eq!(group.relationships.as_ref().unwrap().founder.data.id, 33084);
+    assert_roundtrips(&resp.data);
+}
+
+#[test]
+fn blog_post_roundtrips() {
+    let resp: BlogPostResponse = serde_json::from_str(include_str!("fixtures/blog_post.json")).unwrap();
+    let post = &resp.data;
+    assert_eq!(post.id, BlogPostId(880214));
+    assert_eq!(post.attributes.tags, vec![BlogTag::News, BlogTag::Unknown("writing".to_owned())]);
+    assert!(post.attributes.site_post_tag.is_none());
+    assert!(post.attributes.content.as_ref().unwrap().bbcode.is_some());
+    assert_roundtrips(&resp.data);
+}
+
+#[test]
+fn debug_block_is_parsed() {
+    let resp: BlogPostResponse = serde_json::from_str(include_str!("fixtures/blog_post.json")).unwrap();
+    let debug = resp.debug.unwrap();
+    assert_eq!(debug.duration(), Some(std::time::Duration::from_micros(10_330)));
+    assert!(debug.query_count.is_none());
+
+    let mut raw: serde_json::Value = serde_json::from_str(include_str!("fixtures/blog_post.json")).unwrap();
+    raw.as_object_mut().unwrap().remove("debug");
+    let resp: BlogPostResponse = serde_json::from_value(raw.clone()).unwrap();
+    assert!(resp.debug.is_none());
+    // In case a disabled block is sent empty, rather than left out.
+    raw["debug"] = serde_json::json!([]);
+    let resp: BlogPostResponse = serde_json::from_value(raw).unwrap();
+    assert!(resp.debug.is_none());
+}
+
+#[test]
+fn bookshelf_roundtrips() {
+    let resp: BookshelfResponse = serde_json::from_str(include_str!("fixtures/bookshelf.json")).unwrap();
+    let shelf = &resp.data;
+    assert_eq!(shelf.id, BookshelfId(16299));
+    assert_eq!(shelf.attributes.privacy, Privacy::Public);
+    // An empty color string means no color.
+    assert!(shelf.attributes.color.is_none());
+    assert_eq!(shelf.attributes.icon.type_, "font-awesome");
+    // `None` colors are sent as null, which must be accepted on the way back in.
+    assert_roundtrips(&resp.data);
+}
+
+#[test]
+fn group_threads_roundtrip() {
+    let resp: GroupThreadsResponse =
+        serde_json::from_str(include_str!("fixtures/group_threads.json")).unwrap();
+    assert_eq!(resp.data.len(), 1);
+    let thread = &resp.data[0];
+    assert_eq!(thread.id, GroupThreadId(51122));
+    assert!(thread.attributes.sticky);
+    assert_eq!(resp.meta.total, Some(97));
+    assert_roundtrips(&resp.data);
+}
+
+#[test]
+fn followers_roundtrip() {
+    let resp: FollowersResponse = serde_json::from_str(include_str!("fixtures/followers.json")).unwrap();
+    let follow = &resp.data[0];
+    assert_eq!(follow.id, FollowId(2024768));
+    let relationships = follow.relationships.as_ref().unwrap();
+    assert_eq!(relationships.user.data.id, 33084);
+    assert_eq!(relationships.following.data.id, 211739);
+    assert_roundtrips(&resp.data);
+}
+
+#[test]
+fn follower_pages_are_continued_by_cursor() {
+    let first: FollowersResponse = serde_json::from_str(include_str!("fixtures/followers_page1.json")).unwrap();
+    assert_eq!(first.data.len(), 2);
+    let cursor = first.next_cursor().unwrap();
+    assert!(cursor.is_opaque());
+    let query = Query::new().cursor(Some(&cursor));
+    assert_eq!(query.pairs(), &[
+        ("page[size]".to_owned(), "2".to_owned()),
+        ("page[cursor]".to_owned(), "eyJpZCI6MjAzMTE1N30".to_owned()),
+    ]);
+
+    let last: FollowersResponse = serde_json::from_str(include_str!("fixtures/followers_page2.json")).unwrap();
+    assert_eq!(last.data[0].id, FollowId(2040992));
+    assert!(last.next_cursor().is_none());
+    assert!(Query::new().cursor(None).pairs().is_empty());
+}
+
+#[test]
+fn comments_roundtrip() {
+    let resp: CommentsResponse = serde_json::from_str(include_str!("fixtures/comments.json")).unwrap();
+    let comment = &resp.data[0];
+    assert_eq!(comment.id, CommentId(9912345));
+    // Only sent when the exact source was requested.
+    assert!(comment.attributes.content.is_none());
+    assert_roundtrips(&resp.data);
+}
+
+#[test]
+fn story_tag_roundtrips() {
+    let resp: StoryTagResponse = serde_json::from_str(include_str!("fixtures/story_tag.json")).unwrap();
+    let tag = &resp.data;
+    assert_eq!(tag.id, StoryTagId(47));
+    assert_eq!(tag.attributes.name, "Twilight Sparkle");
+    assert!(tag.relationships.is_none());
+    assert_roundtrips(&resp.data);
+}
+
+#[test]
+fn story_ids_from_urls() {
+    assert_eq!(StoryId::from_url("https://www.fimfiction.net/story/428677/the-cartographers-daughter"),
+        Some(StoryId(428677)));
+    assert_eq!(StoryId::from_url("https://fimfiction.net/s/428677"), Some(StoryId(428677)));
+    assert_eq!(StoryId::from_url("www.fimfiction.net/story/428677"), Some(StoryId(428677)));
+    assert_eq!(StoryId::from_url("https://www.fimfiction.net/user/211739/Pen+Stroke"), None);
+    assert_eq!(StoryId::from_url("https://example.com/story/428677"), None);
+    assert_eq!(StoryId::from_url("not a url"), None);
+}
+
+#[test]
+fn colors_are_normalized() {
+    let color: Color = serde_json::from_str(r#"{"hex": "6B8FB3", "rgb": [107, 143, 179]}"#).unwrap();
+    assert_eq!(color.hex, "#6b8fb3");
+    assert!(color.validate());
+
+    let mismatched: Color = serde_json::from_str(r#"{"hex": "000000", "rgb": [107, 143, 179]}"#).unwrap();
+    assert!(!mismatched.validate());
+    let malformed: Color = serde_json::from_str(r#"{"hex": "blue", "rgb": [0, 0, 255]}"#).unwrap();
+    assert_eq!(malformed.hex, "blue");
+    assert!(!malformed.validate());
+
+    assert_eq!(Color::from_rgb([107, 143, 179]).hex, "#6b8fb3");
+    assert_eq!(Color::from_hex("#6B8FB3").unwrap().rgb, [107, 143, 179]);
+    assert!(Color::from_hex("#6b8fb").is_none());
+}
+
+#[test]
+fn story_prequels_may_be_absent_or_null() {
+    let with_prequel: StoryRelationships = serde_json::from_str(r#"{
+        "author": {"data": {"type": "user", "id": "211739"}},
+        "tags": {"data": []},
+        "prequel": {"data": {"type": "story", "id": "401233"}},
+        "sequel": {"data": null}
+    }"#).unwrap();
+    assert_eq!(with_prequel.prequel().unwrap().id, 401233);
+    assert!(with_prequel.sequel.as_ref().unwrap().data.is_none());
+    assert!(with_prequel.sequel().is_none());
+}
+
+#[test]
+fn unknown_enum_values_are_preserved() {
+    let rating: ContentRating = serde_json::from_str(r#""teen""#).unwrap();
+    assert_eq!(rating, ContentRating::Teen);
+    let status: CompletionStatus = serde_json::from_str(r#""abandoned""#).unwrap();
+    assert_eq!(status, CompletionStatus::Unknown("abandoned".to_owned()));
+    assert_eq!(serde_json::to_string(&status).unwrap(), r#""abandoned""#);
+    let tag_type: TagType = serde_json::from_str(r#""franchise""#).unwrap();
+    assert_eq!(tag_type.as_str(), "franchise");
+}
+
+#[test]
+fn content_rating_predicates() {
+    assert!(ContentRating::Mature.is_mature());
+    assert!(ContentRating::Mature.is_teen_or_above());
+    assert!(!ContentRating::Teen.is_mature());
+    assert!(ContentRating::Teen.is_teen_or_above());
+    assert!(!ContentRating::Everyone.is_teen_or_above());
+    assert!(!ContentRating::Unknown("adult".to_owned()).is_teen_or_above());
+}
+
+#[test]
+fn content_ratings_are_ordered_by_maturity() {
+    let ratings = [ContentRating::Teen, ContentRating::Everyone, ContentRating::Mature];
+    assert_eq!(ratings.iter().max(), Some(&ContentRating::Mature));
+    assert!(ContentRating::Everyone < ContentRating::Teen);
+    assert!(ContentRating::Mature < ContentRating::Unknown("adult".to_owned()));
+    assert!(Privacy::Private < Privacy::Unlisted && Privacy::Unlisted < Privacy::Public);
+}
+
+#[test]
+fn malformed_undocumented_attributes_become_warnings() {
+    let mut raw: serde_json::Value = serde_json::from_str(include_str!("fixtures/story.json")).unwrap();
+    raw["data"]["attributes"]["rating"] = "96%".into();
+    let resp: StoryResponse = serde_json::from_value(raw.clone()).unwrap();
+    assert_eq!(resp.data.attributes.rating, Rating(0));
+    assert_eq!(resp.warnings.len(), 1);
+
+    // Warnings belong to the response they were noticed in.
+    let resp: StoryResponse = serde_json::from_str(include_str!("fixtures/story.json")).unwrap();
+    assert_eq!(resp.data.attributes.rating, Rating(96));
+    assert!(resp.warnings.is_empty());
+
+    // Including those noticed in a resource parsed by itself, which aren't kept for the next response.
+    let story: Story = serde_json::from_value(raw["data"].clone()).unwrap();
+    assert_eq!(story.attributes.rating, Rating(0));
+    let resp: StoryResponse = serde_json::from_str(include_str!("fixtures/story.json")).unwrap();
+    assert!(resp.warnings.is_empty());
+}
+
+#[test]
+fn avatar_sizes_which_arent_numbers_become_warnings() {
+    let mut raw: serde_json::Value = serde_json::from_str(include_str!("fixtures/user.json")).unwrap();
+    raw["data"]["attributes"]["avatar"]["retina"] = "https://cdn-img.fimfiction.net/user/ov33-retina".into();
+    let resp: UserResponse = serde_json::from_value(raw).unwrap();
+    assert_eq!(resp.data.attributes.avatar.sizes.len(), 5);
+    assert_eq!(resp.warnings.len(), 1);
+}
+
+#[test]
+fn endpoint_path_segments_are_percent_encoded() {
+    let path = EndpointPath::new().segment("stories").segment(1234).segment("chapters");
+    assert_eq!(path.as_str(), "stories/1234/chapters");
+    let path = EndpointPath::new().segment("story-tags").segment("a/b?c=d#e f&g");
+    assert_eq!(path.as_str(), "story-tags/a%2Fb%3Fc%3Dd%23e%20f%26g");
+    assert_eq!(EndpointPath::new().segment("..").segment("é.txt").as_str(), "%2E%2E/%C3%A9.txt");
+    assert_eq!(ResourceId { type_: "story".to_owned(), id: 5 }.path().unwrap(), "stories/5");
+}
@@ -0,0 +1,18 @@
+//! Reading the HTML fields as plain text.
+#![cfg(feature = "text")]
+use fimfiction_api::*;
+
+#[test]
+fn html_to_text_strips_tags_and_decodes_entities() {
+    let html = "<p>Twilight &amp; Spike<br/>go <b>north</b>.</p><p>1 &lt; 2</p>";
+    assert_eq!(html_to_text(html), "Twilight & Spike\ngo north.\n1 < 2");
+}
+
+#[test]
+fn html_to_text_handles_description_fixture() {
+    let resp: StoryResponse = serde_json::from_str(include_str!("fixtures/story.json")).unwrap();
+    assert_eq!(
+        html_to_text(&resp.data.attributes.description_html),
+        "Every map has an edge. Hers keeps moving."
+    );
+}
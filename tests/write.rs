@@ -0,0 +1,13 @@
+//! Bodies of the requests which create or modify resources.
+use fimfiction_api::*;
+use serde_json::json;
+
+#[test]
+fn patches_only_send_the_attributes_which_were_set() {
+    let patch = StoryPatch::new().title("The Cartographer's Daughter");
+    assert_eq!(serde_json::to_value(&patch).unwrap(), json!({"title": "The Cartographer's Daughter"}));
+    assert_eq!(serde_json::to_value(StoryPatch::new()).unwrap(), json!({}));
+
+    let patch = ChapterPatch::new().authors_note("Thanks for reading!");
+    assert_eq!(serde_json::to_value(&patch).unwrap(), json!({"authors_note": "Thanks for reading!"}));
+}